@@ -6,8 +6,11 @@ use std::{
 
 use chumsky::Parser;
 use clap::Parser as _parser;
-use nalgebra::Vector2;
 use tp2::{
+    order_parameters::{
+        largest_cluster_fraction, mean_nearest_neighbor_distance, milling, polarization,
+        WindowStatistics,
+    },
     parser::{input_parser, output_parser},
     particle::Frame,
 };
@@ -26,6 +29,28 @@ struct Args {
 
     #[arg(long)]
     capture_directory: Option<PathBuf>,
+
+    /// Add a column with the rotational order around the center of mass
+    #[arg(long)]
+    milling: bool,
+
+    /// Add a column with the fraction of particles in the largest cluster
+    #[arg(long)]
+    largest_cluster: bool,
+
+    /// Add a column with the mean nearest neighbor distance
+    #[arg(long)]
+    nearest_neighbor: bool,
+
+    /// Write mean va, variance, susceptibility and Binder cumulant over the stationary interval
+    #[arg(long)]
+    statistics: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 0.0)]
+    stationary_start: f64,
+
+    #[arg(long)]
+    stationary_end: Option<f64>,
 }
 
 fn main() {
@@ -38,21 +63,54 @@ fn main() {
         .expect("Error parsing input data.");
 
     let mut analysis_file = File::create(args.analysis).unwrap();
+    let mut statistics = WindowStatistics::default();
 
     for frame in output_parser(
         system_info.particles.len(),
         BufReader::new(output_file).lines(),
     ) {
         let Frame { time, particles } = frame;
-        let va = particles
-            .iter()
-            .map(|p| p.velocity_direction)
-            .sum::<Vector2<f64>>()
-            .magnitude()
-            / particles.len() as f64;
-
-        analysis_file
-            .write_fmt(format_args!("{time},{va}\n"))
+        let va = polarization(&particles);
+
+        if time >= args.stationary_start && !args.stationary_end.is_some_and(|end| time > end) {
+            statistics.push(va);
+        }
+
+        let mut row = format!("{time},{va}");
+        if args.milling {
+            let milling = milling(&particles, system_info.space_length);
+            row += &format!(",{milling}");
+        }
+        if args.largest_cluster {
+            let fraction = largest_cluster_fraction(
+                &particles,
+                system_info.interaction_radius,
+                system_info.space_length,
+            );
+            row += &format!(",{fraction}");
+        }
+        if args.nearest_neighbor {
+            let distance = mean_nearest_neighbor_distance(&particles, system_info.space_length);
+            row += &format!(",{distance}");
+        }
+
+        analysis_file.write_fmt(format_args!("{row}\n")).unwrap();
+    }
+
+    if let Some(statistics_path) = args.statistics {
+        let mut statistics_file = File::create(statistics_path).unwrap();
+        statistics_file
+            .write_all(b"samples,mean_va,variance,susceptibility,binder_cumulant\n")
+            .unwrap();
+        statistics_file
+            .write_fmt(format_args!(
+                "{},{},{},{},{}\n",
+                statistics.count,
+                statistics.mean(),
+                statistics.variance(),
+                statistics.susceptibility(system_info.particles.len()),
+                statistics.binder_cumulant(),
+            ))
             .unwrap();
     }
 }
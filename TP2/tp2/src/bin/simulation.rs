@@ -1,21 +1,23 @@
+#![feature(let_chains)]
+
 use std::{
-    collections::BTreeMap,
     fs::{self, File},
     io::{stdout, Write},
-    iter,
 };
 
 use chumsky::Parser;
-use cim::{cim_finder::CimNeighborFinder, neighbor_finder::NeighborFinder, particles::ID};
+use cim::cim_finder::SystemInfo;
 use itertools::Itertools;
-use nalgebra::{Rotation2, Vector2};
-use rand::{distributions::Uniform, rngs::StdRng, Rng, SeedableRng};
 use tp2::{
+    field::{Field, FIELD_HEADER},
+    order_parameters::polarization,
     parser::input_parser,
-    particle::{Frame, InputData, Particle},
+    particle::Frame,
+    simulation::run,
+    stationary::StationaryDetector,
 };
 
-use clap::Parser as _parser;
+use clap::{builder::RangedU64ValueParser, Parser as _parser};
 
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -28,71 +30,38 @@ struct Args {
 
     #[arg(short, long)]
     max_duration: Option<f64>,
-}
 
-fn run<W: Write, F: FnMut(&BTreeMap<ID, Particle>, f64) -> bool>(
-    config: InputData,
-    mut output_writer: W,
-    mut stop_condition: F,
-) {
-    let dt = 1.0;
-    let mut time = 0.0;
-    let mut state: BTreeMap<_, _> = config.particles.into_iter().map(|p| (p.id, p)).collect();
-    let mut rng = if let Some(seed) = config.rng_seed {
-        StdRng::seed_from_u64(seed)
-    } else {
-        StdRng::from_entropy()
-    };
+    /// Stop once the mean of va over this many steps stabilizes
+    #[arg(long)]
+    stationary_window: Option<usize>,
 
-    while !stop_condition(&state, time) {
-        let m = (config.space_length / config.interaction_radius).floor() as usize;
-        let neighbors = CimNeighborFinder::find_neighbors(
-            &state.values().cloned().collect_vec(),
-            cim::cim_finder::SystemInfo {
-                cyclic: true,
-                interaction_radius: config.interaction_radius,
-                space_width: config.space_length,
-                space_height: config.space_length,
-                columns: m,
-                rows: m,
-            },
-        );
-
-        let mut new_state = BTreeMap::new();
-        for (&id, particle) in &state {
-            let sums = neighbors
-                .get_neighbors(id)
-                .chain(iter::once(&id))
-                .map(|i| state[i])
-                .map(|n| n.velocity_direction)
-                .sum::<Vector2<_>>();
-
-            let angle = f64::atan2(sums.y, sums.x)
-                + rng.sample(Uniform::new_inclusive(
-                    -config.noise / 2.0,
-                    config.noise / 2.0,
-                ));
-
-            let new_velocity = Rotation2::new(angle).transform_vector(&Vector2::x());
-
-            new_state.insert(
-                id,
-                Particle {
-                    id,
-                    position: (particle.position + particle.velocity_direction * config.speed * dt)
-                        .apply_into(|f| *f = f.rem_euclid(config.space_length)),
-                    velocity_direction: new_velocity,
-                },
-            );
-        }
-        let frame = Frame {
-            time,
-            particles: state.values().cloned().collect_vec(),
-        };
-        output_writer.write_fmt(format_args!("{frame}")).unwrap();
-        state = new_state;
-        time += dt;
-    }
+    /// Maximum difference between the means of consecutive windows to consider va stationary
+    #[arg(long, default_value_t = 0.01)]
+    stationary_tolerance: f64,
+
+    /// Time to keep simulating after the stationary state is detected
+    #[arg(long, default_value_t = 500.0)]
+    measurement_window: f64,
+
+    /// Where to write the detected transient time, stderr if missing
+    #[arg(long)]
+    transient_output: Option<String>,
+
+    /// Write coarse-grained density and velocity fields to this file
+    #[arg(long)]
+    field_output: Option<String>,
+
+    /// Write the fields every this many frames
+    #[arg(
+        long,
+        default_value_t = 10,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    field_every: usize,
+
+    /// Cells per side of the field grid, the CIM grid resolution if missing
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    field_resolution: Option<usize>,
 }
 
 fn main() {
@@ -104,14 +73,76 @@ fn main() {
         .into_result()
         .expect("Error parsing input data.");
 
-    let writer = if let Some(output) = args.output {
+    let mut writer = if let Some(output) = args.output {
         Box::new(File::create(output).unwrap()) as Box<dyn Write>
     } else {
         Box::new(stdout())
     };
 
-    run(input, writer, |_state, t| {
-        args.max_duration
-            .is_some_and(|max_duration| t > max_duration)
+    let mut field_writer = args.field_output.map(|field_output| {
+        let mut file = File::create(field_output).unwrap();
+        file.write_fmt(format_args!("{FIELD_HEADER}\n")).unwrap();
+        file
+    });
+    let field_resolution = args.field_resolution.unwrap_or_else(|| {
+        ((input.space_length / input.interaction_radius).floor() as usize).max(1)
     });
+    let field_system = SystemInfo {
+        cyclic: true,
+        interaction_radius: input.interaction_radius,
+        space_width: input.space_length,
+        space_height: input.space_length,
+        columns: field_resolution,
+        rows: field_resolution,
+    };
+    let speed = input.speed;
+    let mut frame_count = 0;
+
+    let mut detector = args
+        .stationary_window
+        .map(|window| StationaryDetector::new(window, args.stationary_tolerance));
+
+    run(
+        input,
+        |state, time| {
+            let frame = Frame {
+                time,
+                particles: state.values().cloned().collect_vec(),
+            };
+            writer.write_fmt(format_args!("{frame}")).unwrap();
+
+            if let Some(field_writer) = &mut field_writer
+                && frame_count % args.field_every == 0
+            {
+                let field = Field::from_particles(time, &frame.particles, speed, &field_system);
+                field_writer.write_fmt(format_args!("{field}")).unwrap();
+            }
+            frame_count += 1;
+        },
+        |state, t| {
+            let stationary = detector.as_mut().is_some_and(|detector| {
+                let va = polarization(&state.values().copied().collect_vec());
+                detector
+                    .push(t, va)
+                    .is_some_and(|transient_time| t > transient_time + args.measurement_window)
+            });
+
+            stationary
+                || args
+                    .max_duration
+                    .is_some_and(|max_duration| t > max_duration)
+        },
+    );
+
+    if let Some(detector) = detector {
+        if let Some(transient_time) = detector.transient_time() {
+            if let Some(transient_output) = args.transient_output {
+                fs::write(transient_output, format!("{transient_time}\n")).unwrap();
+            } else {
+                eprintln!("{transient_time}");
+            }
+        } else {
+            eprintln!("The stationary state was not reached.");
+        }
+    }
 }
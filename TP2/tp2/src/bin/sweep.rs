@@ -0,0 +1,234 @@
+use std::{
+    fs::File,
+    io::{stdout, Write},
+    num::NonZeroUsize,
+    ops::Add,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use clap::Parser as _parser;
+use itertools::{iproduct, Itertools};
+use tp2::{
+    order_parameters::{polarization, WindowStatistics},
+    simulation::{random_input, run},
+    stationary::StationaryDetector,
+};
+
+/// List of parameter values, either `start:end:step` (end included) or `a,b,c`.
+#[derive(Debug, Clone)]
+struct Values<T>(Vec<T>);
+
+impl<T> FromStr for Values<T>
+where
+    T: FromStr + Copy + PartialOrd + Add<Output = T>,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<T>()
+                .map_err(|_| format!("Invalid value '{v}'."))
+        };
+
+        if let Some((start, end, step)) = s.split(':').collect_tuple() {
+            let (start, end, step) = (parse(start)?, parse(end)?, parse(step)?);
+            if start + step <= start {
+                return Err("The step must be positive.".to_owned());
+            }
+            Ok(Self(
+                std::iter::successors(Some(start), |&v| Some(v + step))
+                    .take_while(|&v| v <= end)
+                    .collect(),
+            ))
+        } else {
+            s.split(',').map(parse).collect::<Result<_, _>>().map(Self)
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long, default_value = "0:5:0.5")]
+    noises: Values<f64>,
+
+    #[arg(long, default_value = "400")]
+    particle_counts: Values<usize>,
+
+    #[arg(long, default_value = "20")]
+    lengths: Values<f64>,
+
+    #[arg(long, default_value = "0:2:1")]
+    seeds: Values<u64>,
+
+    #[arg(long, default_value_t = 1.0)]
+    interaction_radius: f64,
+
+    #[arg(long, default_value_t = 0.03)]
+    speed: f64,
+
+    #[arg(short, long, default_value_t = 1000.0)]
+    max_duration: f64,
+
+    /// Time from which va is considered stationary when no detection window is given
+    #[arg(long, default_value_t = 500.0)]
+    stationary_start: f64,
+
+    /// Detect the stationary state with a sliding window of this many steps
+    #[arg(long)]
+    stationary_window: Option<usize>,
+
+    #[arg(long, default_value_t = 0.01)]
+    stationary_tolerance: f64,
+
+    /// Time to keep measuring after the stationary state is detected
+    #[arg(long, default_value_t = 500.0)]
+    measurement_window: f64,
+
+    #[arg(short, long)]
+    threads: Option<NonZeroUsize>,
+
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Job {
+    noise: f64,
+    particle_count: usize,
+    length: f64,
+    seed: u64,
+}
+
+#[derive(Debug)]
+struct JobResult {
+    transient_time: Option<f64>,
+    statistics: WindowStatistics,
+}
+
+fn run_job(job: Job, args: &Args) -> JobResult {
+    let input = random_input(
+        job.particle_count,
+        job.length,
+        args.interaction_radius,
+        job.noise,
+        args.speed,
+        job.seed,
+    );
+
+    let mut detector = args
+        .stationary_window
+        .map(|window| StationaryDetector::new(window, args.stationary_tolerance));
+    let mut statistics = WindowStatistics::default();
+
+    run(
+        input,
+        |_state, _time| {},
+        |state, t| {
+            let va = polarization(&state.values().copied().collect_vec());
+            let stationary_start = if let Some(detector) = detector.as_mut() {
+                detector.push(t, va)
+            } else {
+                Some(args.stationary_start)
+            };
+
+            if stationary_start.is_some_and(|start| t >= start) {
+                statistics.push(va);
+            }
+
+            t > args.max_duration
+                || (detector.is_some()
+                    && stationary_start.is_some_and(|start| t > start + args.measurement_window))
+        },
+    );
+
+    JobResult {
+        transient_time: detector.and_then(|detector| detector.transient_time()),
+        statistics,
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let jobs = iproduct!(
+        &args.noises.0,
+        &args.particle_counts.0,
+        &args.lengths.0,
+        &args.seeds.0
+    )
+    .map(|(&noise, &particle_count, &length, &seed)| Job {
+        noise,
+        particle_count,
+        length,
+        seed,
+    })
+    .collect_vec();
+
+    let threads = args
+        .threads
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+
+    let next_job = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let index = next_job.fetch_add(1, Ordering::Relaxed);
+                        let Some(&job) = jobs.get(index) else {
+                            break results;
+                        };
+                        results.push((index, run_job(job, &args)));
+                    }
+                })
+            })
+            .collect_vec();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect_vec()
+    });
+    results.sort_by_key(|(index, _)| *index);
+
+    let mut writer = if let Some(output) = args.output {
+        Box::new(File::create(output).unwrap()) as Box<dyn Write>
+    } else {
+        Box::new(stdout())
+    };
+
+    writer
+        .write_all(b"noise,particle_count,length,density,seed,transient_time,samples,mean_va,variance,susceptibility,binder_cumulant\n")
+        .unwrap();
+    for (index, result) in results {
+        let Job {
+            noise,
+            particle_count,
+            length,
+            seed,
+        } = jobs[index];
+        let JobResult {
+            transient_time,
+            statistics,
+        } = result;
+
+        writer
+            .write_fmt(format_args!(
+                "{noise},{particle_count},{length},{},{seed},{},{},{},{},{},{}\n",
+                particle_count as f64 / length.powi(2),
+                transient_time.map(|t| t.to_string()).unwrap_or_default(),
+                statistics.count,
+                statistics.mean(),
+                statistics.variance(),
+                statistics.susceptibility(particle_count),
+                statistics.binder_cumulant(),
+            ))
+            .unwrap();
+    }
+}
@@ -1,5 +1,8 @@
+#![feature(let_chains)]
+
 use capturable_visualization::VisualizationBuilder;
 use chumsky::Parser;
+use cim::particles::ID;
 use clap::Parser as _parser;
 use nalgebra::{Rotation2, Vector2};
 use nannou::{
@@ -7,14 +10,18 @@ use nannou::{
     prelude::{Rgb, *},
 };
 use std::{
+    collections::{BTreeMap, VecDeque},
     fs::{read_to_string, File},
     io::{BufRead, BufReader},
+    iter::Peekable,
     num::ParseIntError,
     path::PathBuf,
 };
 use tp2::{
+    field::{field_parser, Field},
+    order_parameters::{find_clusters, minimum_image, polarization},
     parser::{input_parser, output_parser},
-    particle::{Frame, InputData},
+    particle::{Frame, InputData, Particle},
 };
 
 #[derive(clap::Parser, Debug)]
@@ -28,6 +35,26 @@ struct Args {
 
     #[arg(long)]
     capture_directory: Option<PathBuf>,
+
+    /// Coarse-grained fields written by the simulation, drawn as a heatmap with arrows
+    #[arg(long)]
+    fields: Option<PathBuf>,
+
+    /// Amount of past positions kept for the trails
+    #[arg(long, default_value_t = 30)]
+    trail_length: usize,
+
+    /// Start with trails shown (toggle with T)
+    #[arg(long)]
+    trails: bool,
+
+    /// Start coloring by cluster instead of heading (toggle with C)
+    #[arg(long)]
+    clusters: bool,
+
+    /// Start with the va(t) plot shown (toggle with P)
+    #[arg(long)]
+    plot: bool,
 }
 
 fn main() {
@@ -35,6 +62,7 @@ fn main() {
     let capture_directory = args.capture_directory.clone();
     let mut visualization = VisualizationBuilder::new(|app| model(app, args))
         .update(update)
+        .event(event)
         .draw(draw);
 
     if let Some(capture_directory) = capture_directory {
@@ -48,6 +76,16 @@ struct Model {
     system_info: InputData,
     frame_iter: Box<dyn Iterator<Item = Frame>>,
     frame: Frame,
+    field_iter: Option<Peekable<Box<dyn Iterator<Item = Field>>>>,
+    field: Option<Field>,
+    show_field: bool,
+    trail_length: usize,
+    trails: BTreeMap<ID, VecDeque<Vector2<f64>>>,
+    show_trails: bool,
+    clusters: Option<BTreeMap<ID, usize>>,
+    cluster_sizes: Vec<usize>,
+    va_history: Vec<(f64, f64)>,
+    show_plot: bool,
 }
 
 fn model(_app: &App, args: Args) -> Model {
@@ -63,12 +101,29 @@ fn model(_app: &App, args: Args) -> Model {
         BufReader::new(output_file).lines(),
     ));
 
+    let field_iter = args.fields.map(|fields| {
+        let fields_file = File::open(fields).unwrap();
+        (Box::new(field_parser(BufReader::new(fields_file).lines()))
+            as Box<dyn Iterator<Item = Field>>)
+            .peekable()
+    });
+
     Model {
         frame: Frame {
             time: -1.0,
             particles: vec![],
         },
         frame_iter,
+        field_iter,
+        field: None,
+        show_field: true,
+        trail_length: args.trail_length,
+        trails: BTreeMap::new(),
+        show_trails: args.trails,
+        clusters: args.clusters.then(BTreeMap::new),
+        cluster_sizes: vec![],
+        va_history: vec![],
+        show_plot: args.plot,
         system_info,
     }
 }
@@ -77,6 +132,36 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
     if let Some(frame) = model.frame_iter.next() {
         model.frame = frame;
 
+        if let Some(field_iter) = &mut model.field_iter {
+            while let Some(field) = field_iter.next_if(|field| field.time <= model.frame.time) {
+                model.field = Some(field);
+            }
+        }
+
+        let space_length = model.system_info.space_length;
+        for particle in &model.frame.particles {
+            let trail = model.trails.entry(particle.id).or_default();
+            // Store unwrapped positions so trails do not jump across periodic edges.
+            let unwrapped = trail.back().map_or(particle.position, |&last| {
+                last + minimum_image(
+                    particle.position - last.map(|v| v.rem_euclid(space_length)),
+                    space_length,
+                )
+            });
+            trail.push_back(unwrapped);
+            if trail.len() > model.trail_length {
+                trail.pop_front();
+            }
+        }
+
+        if model.clusters.is_some() {
+            update_clusters(model);
+        }
+
+        model
+            .va_history
+            .push((model.frame.time, polarization(&model.frame.particles)));
+
         //let colors = ["d03e2d", "e97c54", "ee9262", "e6bca5", "f4e0d8"]
         //.map(parse_hex_color)
         //.map(|c| Rgba::<f32>::from(c.unwrap().into_format()))
@@ -88,16 +173,158 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
     }
 }
 
+fn update_clusters(model: &mut Model) {
+    let clusters = find_clusters(
+        &model.frame.particles,
+        model.system_info.interaction_radius,
+        model.system_info.space_length,
+    );
+    model.cluster_sizes = vec![0; clusters.values().max().map_or(0, |&label| label + 1)];
+    for &label in clusters.values() {
+        model.cluster_sizes[label] += 1;
+    }
+    model.clusters = Some(clusters);
+}
+
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    if let KeyPressed(key) = event {
+        match key {
+            Key::T => model.show_trails = !model.show_trails,
+            Key::C => {
+                if model.clusters.is_some() {
+                    model.clusters = None;
+                } else {
+                    update_clusters(model);
+                }
+            }
+            Key::P => model.show_plot = !model.show_plot,
+            Key::F => model.show_field = !model.show_field,
+            _ => {}
+        }
+    }
+}
+
 fn parse_hex_color(s: &str) -> Result<Rgb<u8>, ParseIntError> {
     u32::from_str_radix(s, 16).map(rgb_u32)
 }
 
-fn draw(_app: &App, model: &Model, draw: &Draw) {
-    let draw = draw.scale(1.0 / model.system_info.space_length as f32);
-    draw.background().color(parse_hex_color("213437").unwrap());
-    for (_i, particle) in model.frame.particles.iter().enumerate() {
+fn draw_field(field: &Field, space_length: f32, draw: &Draw) {
+    let cell_size = vec2(
+        space_length / field.columns as f32,
+        space_length / field.rows as f32,
+    );
+    let max_density = field.density.iter().copied().fold(0.0, f64::max);
+    let max_speed = field
+        .velocity
+        .iter()
+        .map(|v| v.magnitude())
+        .fold(0.0, f64::max);
+
+    for row in 0..field.rows {
+        for column in 0..field.columns {
+            let (density, velocity) = field.get(row, column);
+            let center = (vec2(column as f32, row as f32) + 0.5) * cell_size;
+
+            if max_density > 0.0 {
+                let value = (density / max_density) as f32;
+                draw.rect().xy(center).wh(cell_size).color(hsva(
+                    0.7 - 0.7 * value,
+                    0.8,
+                    value,
+                    0.6,
+                ));
+            }
+
+            if max_speed > 0.0 && velocity.magnitude() > 0.0 {
+                let arrow = vec2(velocity.x as f32, velocity.y as f32) / max_speed as f32
+                    * cell_size.min_element()
+                    * 0.9;
+                draw.arrow()
+                    .weight(0.04 * cell_size.min_element())
+                    .head_length(0.2 * cell_size.min_element())
+                    .head_width(0.15 * cell_size.min_element())
+                    .points(center - arrow / 2.0, center + arrow / 2.0)
+                    .color(rgba(1.0, 1.0, 1.0, 0.8));
+            }
+        }
+    }
+}
+
+fn particle_color(model: &Model, particle: &Particle, alpha: f32) -> Hsva {
+    if let Some(clusters) = &model.clusters {
+        let label = clusters[&particle.id];
+        if model.cluster_sizes[label] > 1 {
+            // Spread consecutive labels around the hue circle with the golden ratio.
+            hsva((label as f32 * 0.618_034).fract(), 1.0, 1.0, alpha)
+        } else {
+            hsva(0.0, 0.0, 0.6, alpha)
+        }
+    } else {
         let angle =
             Rotation2::rotation_between(&Vector2::x(), &particle.velocity_direction).angle();
+        hsva(angle.rem_euclid(TAU_F64) as f32 / TAU, 1.0, 1.0, alpha)
+    }
+}
+
+fn draw_trail(model: &Model, particle: &Particle, draw: &Draw) {
+    let Some(trail) = model.trails.get(&particle.id) else {
+        return;
+    };
+    let Some(&last) = trail.back() else {
+        return;
+    };
+    let offset = particle.position - last;
+    let points = trail.iter().map(|&p| p + offset).collect::<Vec<_>>();
+
+    for (i, segment) in points.windows(2).enumerate() {
+        let alpha = 0.4 * (i + 1) as f32 / points.len() as f32;
+        draw.line()
+            .weight(0.02)
+            .start(vec2(segment[0].x as f32, segment[0].y as f32))
+            .end(vec2(segment[1].x as f32, segment[1].y as f32))
+            .color(particle_color(model, particle, alpha));
+    }
+}
+
+fn draw_plot(model: &Model, draw: &Draw) {
+    let origin = vec2(0.03, 0.03);
+    let size = vec2(0.35, 0.2);
+    draw.rect()
+        .xy(origin + size / 2.0)
+        .wh(size)
+        .color(rgba(0.0, 0.0, 0.0, 0.6));
+
+    let (Some(&(first_time, _)), Some(&(last_time, _))) =
+        (model.va_history.first(), model.va_history.last())
+    else {
+        return;
+    };
+    let duration = (last_time - first_time).max(1.0);
+    draw.polyline()
+        .weight(0.003)
+        .points(model.va_history.iter().map(|&(time, va)| {
+            origin + vec2(((time - first_time) / duration) as f32, va as f32) * size
+        }))
+        .color(WHITE);
+}
+
+fn draw(_app: &App, model: &Model, base_draw: &Draw) {
+    let draw = base_draw.scale(1.0 / model.system_info.space_length as f32);
+    draw.background().color(parse_hex_color("213437").unwrap());
+
+    if let Some(field) = &model.field
+        && model.show_field
+    {
+        draw_field(field, model.system_info.space_length as f32, &draw);
+    }
+
+    if model.show_trails {
+        for particle in &model.frame.particles {
+            draw_trail(model, particle, &draw);
+        }
+    }
+
+    for (_i, particle) in model.frame.particles.iter().enumerate() {
         let tgt = particle.position + particle.velocity_direction * 0.25;
         draw.arrow()
             .weight(0.025)
@@ -107,6 +334,10 @@ fn draw(_app: &App, model: &Model, draw: &Draw) {
             )
             //.color(colors[_i % colors.len()]);
             //.color(gradient.get(angle.rem_euclid(TAU_F64) as f32 / TAU));
-            .color(hsva(angle.rem_euclid(TAU_F64) as f32 / TAU, 1.0, 1.0, 0.4));
+            .color(particle_color(model, particle, 0.4));
+    }
+
+    if model.show_plot {
+        draw_plot(model, base_draw);
     }
 }
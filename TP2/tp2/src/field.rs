@@ -0,0 +1,128 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Lines},
+    iter::Peekable,
+};
+
+use cim::cim_finder::SystemInfo;
+use nalgebra::Vector2;
+
+use crate::particle::Particle;
+
+pub const FIELD_HEADER: &str = "time,row,column,density,vx,vy";
+
+/// Coarse-grained density and mean velocity on a grid, stored row by row.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub time: f64,
+    pub rows: usize,
+    pub columns: usize,
+    pub density: Vec<f64>,
+    pub velocity: Vec<Vector2<f64>>,
+}
+
+impl Field {
+    pub fn from_particles(
+        time: f64,
+        particles: &[Particle],
+        speed: f64,
+        system: &SystemInfo,
+    ) -> Self {
+        let cell_count = system.rows * system.columns;
+        let mut counts = vec![0usize; cell_count];
+        let mut velocity = vec![Vector2::zeros(); cell_count];
+
+        for particle in particles {
+            let cell = system.cell_index(particle.position);
+            // Particles sitting exactly on the upper border belong to the last cell.
+            let index =
+                cell.y.min(system.rows - 1) * system.columns + cell.x.min(system.columns - 1);
+            counts[index] += 1;
+            velocity[index] += particle.velocity_direction * speed;
+        }
+
+        let cell_area = system.cell_size().product();
+        Self {
+            time,
+            rows: system.rows,
+            columns: system.columns,
+            density: counts.iter().map(|&c| c as f64 / cell_area).collect(),
+            velocity: velocity
+                .into_iter()
+                .zip(&counts)
+                .map(|(v, &c)| if c > 0 { v / c as f64 } else { v })
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> (f64, Vector2<f64>) {
+        let index = row * self.columns + column;
+        (self.density[index], self.velocity[index])
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let (density, velocity) = self.get(row, column);
+                f.write_fmt(format_args!(
+                    "{},{row},{column},{density},{},{}\n",
+                    self.time, velocity.x, velocity.y
+                ))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct FieldReader<I: Iterator> {
+    lines: Peekable<I>,
+}
+
+impl<I: Iterator<Item = String>> Iterator for FieldReader<I> {
+    type Item = Field;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parse_line = |line: &str| {
+            let values: [f64; 6] = line
+                .split(',')
+                .map(|v| v.parse().unwrap())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            values
+        };
+
+        let [time, ..] = parse_line(self.lines.peek()?);
+        let mut rows = 0;
+        let mut columns = 0;
+        let mut density = vec![];
+        let mut velocity = vec![];
+        while let Some(line) = self.lines.next_if(|line| parse_line(line)[0] == time) {
+            let [_, row, column, d, vx, vy] = parse_line(&line);
+            rows = rows.max(row as usize + 1);
+            columns = columns.max(column as usize + 1);
+            density.push(d);
+            velocity.push(Vector2::new(vx, vy));
+        }
+
+        Some(Field {
+            time,
+            rows,
+            columns,
+            density,
+            velocity,
+        })
+    }
+}
+
+pub fn field_parser<B: BufRead>(file: Lines<B>) -> FieldReader<impl Iterator<Item = String>> {
+    FieldReader {
+        lines: file
+            .map(Result::unwrap)
+            .filter(|line| line != FIELD_HEADER)
+            .peekable(),
+    }
+}
@@ -1,2 +1,6 @@
+pub mod field;
+pub mod order_parameters;
 pub mod parser;
 pub mod particle;
+pub mod simulation;
+pub mod stationary;
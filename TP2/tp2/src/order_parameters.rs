@@ -0,0 +1,175 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap, VecDeque},
+    f64::consts::TAU,
+};
+
+use cim::{
+    cim_finder::{CimNeighborFinder, SystemInfo},
+    neighbor_finder::NeighborFinder,
+    particles::ID,
+};
+use itertools::Itertools;
+use nalgebra::Vector2;
+
+use crate::particle::Particle;
+
+/// Shortest displacement between two points of a periodic square of side `space_length`.
+pub fn minimum_image(delta: Vector2<f64>, space_length: f64) -> Vector2<f64> {
+    delta.map(|d| d - space_length * (d / space_length).round())
+}
+
+/// Polarization `va`, the modulus of the mean heading.
+pub fn polarization(particles: &[Particle]) -> f64 {
+    particles
+        .iter()
+        .map(|p| p.velocity_direction)
+        .sum::<Vector2<f64>>()
+        .magnitude()
+        / particles.len() as f64
+}
+
+/// Center of mass of a periodic system, taking the circular mean on every axis.
+pub fn center_of_mass(particles: &[Particle], space_length: f64) -> Vector2<f64> {
+    let angles = particles
+        .iter()
+        .map(|p| p.position * TAU / space_length)
+        .collect_vec();
+    let cos = angles.iter().map(|a| a.map(f64::cos)).sum::<Vector2<f64>>();
+    let sin = angles.iter().map(|a| a.map(f64::sin)).sum::<Vector2<f64>>();
+
+    sin.zip_map(&cos, |s, c| {
+        (f64::atan2(s, c) * space_length / TAU).rem_euclid(space_length)
+    })
+}
+
+/// Rotational order parameter (milling) around the center of mass.
+pub fn milling(particles: &[Particle], space_length: f64) -> f64 {
+    let center = center_of_mass(particles, space_length);
+    particles
+        .iter()
+        .filter_map(|p| {
+            let radial = minimum_image(p.position - center, space_length);
+            let distance = radial.magnitude();
+            (distance > 0.0).then(|| radial.perp(&p.velocity_direction) / distance)
+        })
+        .sum::<f64>()
+        .abs()
+        / particles.len() as f64
+}
+
+/// Labels every particle with the cluster it belongs to, two particles being connected when
+/// they are within `interaction_radius`. Labels are sorted by cluster size, so `0` is the largest.
+pub fn find_clusters(
+    particles: &[Particle],
+    interaction_radius: f64,
+    space_length: f64,
+) -> BTreeMap<ID, usize> {
+    let m = (space_length / interaction_radius).floor() as usize;
+    let neighbors = CimNeighborFinder::find_neighbors(
+        particles,
+        SystemInfo {
+            cyclic: true,
+            interaction_radius,
+            space_width: space_length,
+            space_height: space_length,
+            columns: m,
+            rows: m,
+        },
+    );
+
+    let mut clusters: Vec<Vec<ID>> = vec![];
+    let mut visited = BTreeMap::new();
+    for particle in particles {
+        if visited.contains_key(&particle.id) {
+            continue;
+        }
+
+        let mut cluster = vec![];
+        let mut pending = VecDeque::from([particle.id]);
+        visited.insert(particle.id, clusters.len());
+        while let Some(id) = pending.pop_front() {
+            cluster.push(id);
+            for &neighbor in neighbors.get_neighbors(id) {
+                if let Entry::Vacant(entry) = visited.entry(neighbor) {
+                    entry.insert(clusters.len());
+                    pending.push_back(neighbor);
+                }
+            }
+        }
+        clusters.push(cluster);
+    }
+
+    clusters
+        .into_iter()
+        .sorted_by_key(|cluster| std::cmp::Reverse(cluster.len()))
+        .enumerate()
+        .flat_map(|(label, cluster)| cluster.into_iter().map(move |id| (id, label)))
+        .collect()
+}
+
+/// Fraction of the particles that belong to the largest cluster.
+pub fn largest_cluster_fraction(
+    particles: &[Particle],
+    interaction_radius: f64,
+    space_length: f64,
+) -> f64 {
+    let largest = find_clusters(particles, interaction_radius, space_length)
+        .values()
+        .filter(|&&label| label == 0)
+        .count();
+
+    largest as f64 / particles.len() as f64
+}
+
+/// Mean over all particles of the distance to their nearest neighbor.
+pub fn mean_nearest_neighbor_distance(particles: &[Particle], space_length: f64) -> f64 {
+    particles
+        .iter()
+        .map(|p| {
+            particles
+                .iter()
+                .filter(|other| other.id != p.id)
+                .map(|other| minimum_image(other.position - p.position, space_length).magnitude())
+                .fold(f64::INFINITY, f64::min)
+        })
+        .sum::<f64>()
+        / particles.len() as f64
+}
+
+/// Moments of `va` accumulated over a stationary time interval.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowStatistics {
+    pub count: usize,
+    sum: f64,
+    sum_squares: f64,
+    sum_fourth_powers: f64,
+}
+
+impl WindowStatistics {
+    pub fn push(&mut self, va: f64) {
+        self.count += 1;
+        self.sum += va;
+        self.sum_squares += va.powi(2);
+        self.sum_fourth_powers += va.powi(4);
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    pub fn variance(&self) -> f64 {
+        self.sum_squares / self.count as f64 - self.mean().powi(2)
+    }
+
+    /// Susceptibility `N * var(va)` for a system of `particle_count` particles.
+    pub fn susceptibility(&self, particle_count: usize) -> f64 {
+        particle_count as f64 * self.variance()
+    }
+
+    /// Binder cumulant `1 - <va^4> / (3 <va^2>^2)`.
+    pub fn binder_cumulant(&self) -> f64 {
+        let second = self.sum_squares / self.count as f64;
+        let fourth = self.sum_fourth_powers / self.count as f64;
+        1.0 - fourth / (3.0 * second.powi(2))
+    }
+}
@@ -0,0 +1,106 @@
+use std::{collections::BTreeMap, iter};
+
+use cim::{cim_finder::CimNeighborFinder, neighbor_finder::NeighborFinder, particles::ID};
+use itertools::Itertools;
+use nalgebra::{Rotation2, Vector2};
+use rand::{distributions::Uniform, rngs::StdRng, Rng, SeedableRng};
+
+use crate::particle::{InputData, Particle};
+
+/// Runs the Vicsek model, calling `on_frame` with every state before advancing it until
+/// `stop_condition` holds.
+pub fn run<C, F>(config: InputData, mut on_frame: C, mut stop_condition: F)
+where
+    C: FnMut(&BTreeMap<ID, Particle>, f64),
+    F: FnMut(&BTreeMap<ID, Particle>, f64) -> bool,
+{
+    let dt = 1.0;
+    let mut time = 0.0;
+    let mut state: BTreeMap<_, _> = config.particles.into_iter().map(|p| (p.id, p)).collect();
+    let mut rng = if let Some(seed) = config.rng_seed {
+        StdRng::seed_from_u64(seed)
+    } else {
+        StdRng::from_entropy()
+    };
+
+    while !stop_condition(&state, time) {
+        let m = (config.space_length / config.interaction_radius).floor() as usize;
+        let neighbors = CimNeighborFinder::find_neighbors(
+            &state.values().cloned().collect_vec(),
+            cim::cim_finder::SystemInfo {
+                cyclic: true,
+                interaction_radius: config.interaction_radius,
+                space_width: config.space_length,
+                space_height: config.space_length,
+                columns: m,
+                rows: m,
+            },
+        );
+
+        let mut new_state = BTreeMap::new();
+        for (&id, particle) in &state {
+            let sums = neighbors
+                .get_neighbors(id)
+                .chain(iter::once(&id))
+                .map(|i| state[i])
+                .map(|n| n.velocity_direction)
+                .sum::<Vector2<_>>();
+
+            let angle = f64::atan2(sums.y, sums.x)
+                + rng.sample(Uniform::new_inclusive(
+                    -config.noise / 2.0,
+                    config.noise / 2.0,
+                ));
+
+            let new_velocity = Rotation2::new(angle).transform_vector(&Vector2::x());
+
+            new_state.insert(
+                id,
+                Particle {
+                    id,
+                    position: (particle.position + particle.velocity_direction * config.speed * dt)
+                        .apply_into(|f| *f = f.rem_euclid(config.space_length)),
+                    velocity_direction: new_velocity,
+                },
+            );
+        }
+        on_frame(&state, time);
+        state = new_state;
+        time += dt;
+    }
+}
+
+/// Generates `particle_count` particles with uniformly random positions and headings, the same
+/// way `scripts/generate.py` does.
+pub fn random_input(
+    particle_count: usize,
+    space_length: f64,
+    interaction_radius: f64,
+    noise: f64,
+    speed: f64,
+    seed: u64,
+) -> InputData {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let particles = (0..particle_count)
+        .map(|id| Particle {
+            id,
+            position: Vector2::new(
+                rng.gen_range(0.0..space_length),
+                rng.gen_range(0.0..space_length),
+            ),
+            velocity_direction: Rotation2::new(
+                rng.gen_range(-std::f64::consts::PI..std::f64::consts::PI),
+            )
+            .transform_vector(&Vector2::x()),
+        })
+        .collect();
+
+    InputData {
+        rng_seed: Some(rng.gen()),
+        space_length,
+        interaction_radius,
+        noise,
+        speed,
+        particles,
+    }
+}
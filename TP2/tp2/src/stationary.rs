@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+/// Detects when `va` reaches a stationary state by comparing the means of two consecutive
+/// sliding windows of samples.
+#[derive(Debug, Clone)]
+pub struct StationaryDetector {
+    window: usize,
+    tolerance: f64,
+    samples: VecDeque<(f64, f64)>,
+    transient_time: Option<f64>,
+}
+
+impl StationaryDetector {
+    pub fn new(window: usize, tolerance: f64) -> Self {
+        assert!(
+            window > 0,
+            "The stationary window must hold at least one sample."
+        );
+        Self {
+            window,
+            tolerance,
+            samples: VecDeque::with_capacity(2 * window),
+            transient_time: None,
+        }
+    }
+
+    /// Feeds the sample of `va` at `time` and returns the transient time once it was detected.
+    pub fn push(&mut self, time: f64, va: f64) -> Option<f64> {
+        if self.transient_time.is_some() {
+            return self.transient_time;
+        }
+
+        self.samples.push_back((time, va));
+        if self.samples.len() > 2 * self.window {
+            self.samples.pop_front();
+        }
+
+        if self.samples.len() == 2 * self.window {
+            let mean = |samples: &mut dyn Iterator<Item = &(f64, f64)>| {
+                samples.map(|(_, va)| va).sum::<f64>() / self.window as f64
+            };
+            let previous = mean(&mut self.samples.iter().take(self.window));
+            let current = mean(&mut self.samples.iter().skip(self.window));
+
+            if (current - previous).abs() <= self.tolerance {
+                self.transient_time = Some(self.samples[self.window].0);
+            }
+        }
+
+        self.transient_time
+    }
+
+    pub fn transient_time(&self) -> Option<f64> {
+        self.transient_time
+    }
+}
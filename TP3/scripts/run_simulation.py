@@ -67,8 +67,13 @@ def run_simulation(input_data: str):
     return pd.read_csv(
         StringIO(analysis),
         header=None,
-        names=["t", "ball_count", "kinetic_energy"],
-        dtype={"t": np.float64, "ball_count": np.uint64, "kinetic_energy": np.float64},
+        names=["t", "ball_count", "kinetic_energy", "energy_loss"],
+        dtype={
+            "t": np.float64,
+            "ball_count": np.uint64,
+            "kinetic_energy": np.float64,
+            "energy_loss": np.float64,
+        },
     )
 
 def run_multiple_ys():
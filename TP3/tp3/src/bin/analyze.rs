@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fs::{read_to_string, File},
     io::{BufRead, BufReader, Write},
     path::PathBuf,
@@ -11,6 +12,11 @@ use pool::{
     parser::{input_parser, output_parser},
     Float,
 };
+use tp3::{
+    collisions::CollisionAgainst,
+    event_log::{event_log_parser, LoggedEvent},
+    pressure::{parse_window, PressureAccumulator, PRESSURE_HEADER},
+};
 
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -23,6 +29,47 @@ struct Args {
 
     #[arg(short, long)]
     analysis: PathBuf,
+
+    /// Event log written by the simulation, to compute the collision statistics
+    #[arg(long)]
+    event_log: Option<PathBuf>,
+
+    /// Collision frequency, mean free time and mean free path of the balls
+    #[arg(long, requires = "event_log")]
+    collision_statistics: Option<PathBuf>,
+
+    /// Times between consecutive collisions of every ball against other balls, one per line
+    #[arg(long, requires = "event_log")]
+    collision_times: Option<PathBuf>,
+
+    /// Pressure on every wall and kinetic temperature over time windows
+    #[arg(long, requires = "event_log")]
+    pressure: Option<PathBuf>,
+
+    /// Duration of the windows of the pressure output
+    #[arg(long, default_value_t = 1.0, value_parser = parse_window)]
+    pressure_window: Float,
+}
+
+/// Collisions between balls in the event log and the time between consecutive collisions of
+/// the same ball.
+fn collision_times(events: &[LoggedEvent]) -> (usize, Vec<Float>) {
+    let mut last_collisions = BTreeMap::new();
+    let mut collisions = 0;
+    let mut times = vec![];
+
+    for event in events {
+        if let CollisionAgainst::Ball(id1, id2) = event.event {
+            collisions += 1;
+            for id in [id1, id2] {
+                if let Some(last) = last_collisions.insert(id, event.time) {
+                    times.push(event.time - last);
+                }
+            }
+        }
+    }
+
+    (collisions, times)
 }
 
 fn main() {
@@ -36,16 +83,100 @@ fn main() {
 
     let mut analysis_file = File::create(args.analysis).unwrap();
 
+    let mut previous_energies = BTreeMap::new();
+    // Time integral of the mean speed of the balls, to get the mean free path.
+    let mut speed_integral = 0.0;
+    let mut last_speed: Option<(Float, Float)> = None;
+    // Kinetic energy and ball count of every frame, one per event after the first one.
+    let mut frame_energies = vec![];
     for frame in output_parser(BufReader::new(output_file).lines()) {
-        let Frame { time, balls } = frame;
-        let energy: Float = balls
+        let Frame { time, mut balls } = frame;
+        system_info.restore_properties(&mut balls);
+        let energies: BTreeMap<_, Float> = balls
             .iter()
-            .map(|p| 0.5 * system_info.ball_mass * p.velocity.magnitude().powi(2))
-            .sum();
+            .map(|p| (p.id, 0.5 * p.mass * p.velocity.magnitude().powi(2)))
+            .collect();
+        let energy: Float = energies.values().sum();
         let ball_count = balls.len();
 
+        // Kinetic energy lost to the cloth friction and the inelastic collisions since the
+        // previous frame. Only compare the balls still on the table, so balls falling into the
+        // holes do not count as lost.
+        let energy_loss: Float = energies
+            .iter()
+            .filter_map(|(id, energy)| previous_energies.get(id).map(|previous| previous - energy))
+            .sum();
+        previous_energies = energies;
+
+        if let Some((last_time, speed)) = last_speed {
+            speed_integral += speed * (time - last_time);
+        }
+        let mean_speed = balls.iter().map(|p| p.velocity.magnitude()).sum::<Float>()
+            / ball_count.max(1) as Float;
+        last_speed = Some((time, mean_speed));
+        frame_energies.push((energy, ball_count));
+
         analysis_file
-            .write_fmt(format_args!("{time},{ball_count},{energy}\n"))
+            .write_fmt(format_args!("{time},{ball_count},{energy},{energy_loss}\n"))
             .unwrap();
     }
+
+    let Some(event_log) = args.event_log else {
+        return;
+    };
+    let log_file = File::open(event_log).unwrap();
+    let events = event_log_parser(BufReader::new(log_file).lines()).collect::<Vec<_>>();
+    let (collisions, times) = collision_times(&events);
+    let duration = last_speed.map_or(0.0, |(time, _)| time);
+
+    if let Some(path) = args.collision_times {
+        let mut times_file = File::create(path).unwrap();
+        for time in &times {
+            times_file.write_fmt(format_args!("{time}\n")).unwrap();
+        }
+    }
+
+    if let Some(path) = args.collision_statistics {
+        let collision_frequency = collisions as Float / duration;
+        let mean_free_time = times.iter().sum::<Float>() / times.len() as Float;
+        let mean_speed = speed_integral / duration;
+        let mean_free_path = mean_speed * mean_free_time;
+
+        let mut statistics_file = File::create(path).unwrap();
+        statistics_file
+            .write_fmt(format_args!(
+                "collisions,duration,collision_frequency,mean_free_time,mean_speed,mean_free_path\n\
+                {collisions},{duration},{collision_frequency},{mean_free_time},{mean_speed},{mean_free_path}\n"
+            ))
+            .unwrap();
+    }
+
+    if let Some(path) = args.pressure {
+        let Some(&(energy, ball_count)) = frame_energies.first() else {
+            eprintln!("The output has no frames, skipping the pressure.");
+            return;
+        };
+        let mut accumulator = PressureAccumulator::new(
+            args.pressure_window,
+            system_info.table_width,
+            system_info.table_height,
+            energy,
+            ball_count,
+        );
+        let mut pressure_file = File::create(path).unwrap();
+        pressure_file
+            .write_fmt(format_args!("{PRESSURE_HEADER}\n"))
+            .unwrap();
+
+        for (event, &(energy, ball_count)) in events.iter().zip(&frame_energies[1..]) {
+            for window in
+                accumulator.push(event.time, &event.event, event.impulse, energy, ball_count)
+            {
+                pressure_file.write_fmt(format_args!("{window}")).unwrap();
+            }
+        }
+        if let Some(window) = accumulator.finish(duration) {
+            pressure_file.write_fmt(format_args!("{window}")).unwrap();
+        }
+    }
 }
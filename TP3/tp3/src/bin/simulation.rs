@@ -5,15 +5,26 @@ use cim::particles::ID;
 use std::{
     collections::BTreeMap,
     fs::{self, File},
-    io::{stdout, Write},
+    io::{self, stdout, Write},
 };
 
 use itertools::Itertools;
 use nalgebra::Vector2;
 use pool::{
-    models::{Ball, Frame, InputData},
+    models::{Ball, Cushion, Frame, InputData, Pocket},
     parser::input_parser,
-    Float, HOLE_POSITIONS,
+    Float, WHITE_BALL_ID,
+};
+use tp3::{
+    cells::CellGrid,
+    collisions::{
+        find_collision_against_cushion, find_collision_against_wall,
+        find_collision_between_balls, Collision, CollisionAgainst, Restitution,
+    },
+    event_log::{LoggedEvent, EVENT_LOG_HEADER},
+    events::EventQueue,
+    motion::{advance, stop_time},
+    pressure::{kinetic_energy, parse_window, PressureAccumulator, PRESSURE_HEADER},
 };
 
 use clap::Parser as _parser;
@@ -29,149 +40,190 @@ struct Args {
 
     #[arg(short, long)]
     max_duration: Option<Float>,
-}
-
-#[derive(Debug, Copy, Clone)]
-struct Collision {
-    time: Float,
-    info: CollisionAgainst,
-}
-
-#[derive(Debug, Copy, Clone)]
-enum WallType {
-    Horizontal,
-    Vertical,
-}
-
-#[derive(Debug, Copy, Clone)]
-enum CollisionAgainst {
-    Ball(ID, ID),
-    Wall(ID, WallType),
-    Hole(ID),
-}
 
-fn find_collision_between_balls(b1: &Ball, b2: &Ball, radius_sum: Float) -> Option<Float> {
-    let delta_v = b2.velocity - b1.velocity;
-    let delta_r = b2.position - b1.position;
-    let sigma = radius_sum;
-    let d = (delta_v.dot(&delta_r).powi(2))
-        - delta_v.dot(&delta_v) * (delta_r.dot(&delta_r) - sigma.powi(2));
-
-    (delta_v.dot(&delta_r) < 0.0 && d >= 0.0)
-        .then(|| -(delta_v.dot(&delta_r) + d.sqrt()) / (delta_v.dot(&delta_v)))
+    /// Writes every event with the balls involved and the transferred impulse
+    #[arg(long)]
+    event_log: Option<String>,
+
+    /// Writes the pressure on every wall and the kinetic temperature over time windows
+    #[arg(long)]
+    pressure_output: Option<String>,
+
+    /// Duration of the windows of the pressure output
+    #[arg(long, default_value_t = 1.0, value_parser = parse_window)]
+    pressure_window: Float,
+
+    /// Normal restitution coefficient of the collisions between balls
+    #[arg(long, default_value_t = 1.0)]
+    ball_restitution: Float,
+
+    /// Normal restitution coefficient of the collisions against the walls
+    #[arg(long, default_value_t = 1.0)]
+    wall_restitution: Float,
+
+    /// Collisions with a smaller normal speed are elastic, to avoid inelastic collapse
+    #[arg(long, default_value_t = 0.0)]
+    restitution_threshold: Float,
+
+    /// Deceleration of the balls from the friction with the cloth. The simulation ends when
+    /// every ball is at rest.
+    #[arg(long, default_value_t = 0.0)]
+    friction_deceleration: Float,
+
+    /// Speed of the white ball, replacing its velocity in the input
+    #[arg(long)]
+    cue_speed: Option<Float>,
+
+    /// Direction of the shot in degrees, counterclockwise from the x axis
+    #[arg(long, default_value_t = 0.0, requires = "cue_speed", allow_negative_numbers = true)]
+    cue_angle: Float,
+
+    /// Height of the white ball relative to the center of the table, replacing its position in
+    /// the input
+    #[arg(long, allow_negative_numbers = true)]
+    cue_offset: Option<Float>,
+
+    /// Runs until this time, reverses every velocity and runs back for the same duration, then
+    /// prints the distance of the balls to their initial positions. The holes are disabled and
+    /// only the forward run is written to the output. Only elastic runs without friction are
+    /// reversible.
+    #[arg(long, conflicts_with_all = ["event_log", "pressure_output", "max_duration"])]
+    reverse_at: Option<Float>,
 }
 
-fn find_collision_against_wall(ball: &Ball, config: &InputData) -> Option<(Float, WallType)> {
-    let radius = config.ball_radius;
-
-    let time_x = if ball.velocity.x > 0.0 {
-        Some((config.table_width - radius - ball.position.x) / ball.velocity.x)
-    } else if ball.velocity.x < 0.0 {
-        Some((radius - ball.position.x) / ball.velocity.x)
-    } else {
-        None
-    }
-    .map(|t| (t, WallType::Vertical));
-
-    let time_y = if ball.velocity.y > 0.0 {
-        Some((config.table_height - radius - ball.position.y) / ball.velocity.y)
-    } else if ball.velocity.y < 0.0 {
-        Some((radius - ball.position.y) / ball.velocity.y)
-    } else {
-        None
-    }
-    .map(|t| (t, WallType::Horizontal));
-
-    time_x
-        .into_iter()
-        .chain(time_y.into_iter())
-        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
-}
-
-fn find_earliest_collision(
-    state: &[&Ball],
-    holes: &[Vector2<Float>],
+/// Schedules every collision of the ball `id` with the walls, the cushions, the holes and the
+/// balls in the neighboring cells except the ones in `skip`, when it leaves its cell and when it
+/// stops.
+#[allow(clippy::too_many_arguments)]
+fn predict_collisions(
+    id: ID,
+    state: &BTreeMap<ID, Ball>,
+    grid: &CellGrid,
+    holes: &[Pocket],
     config: &InputData,
-) -> Option<Collision> {
-    let mut earliest: Option<Collision> = None;
-    for (ball_1, ball_2) in state.iter().tuple_combinations() {
-        if let Some(time) = find_collision_between_balls(ball_1, ball_2, config.ball_radius * 2.0)
-            && earliest.map(|e| time < e.time).unwrap_or(true)
+    deceleration: Float,
+    time: Float,
+    skip: &[ID],
+    queue: &mut EventQueue,
+) {
+    let ball = &state[&id];
+
+    for other in grid
+        .neighbors(id)
+        .filter(|&other| other != id && !skip.contains(&other))
+        .map(|other| &state[&other])
+    {
+        if let Some(t) =
+            find_collision_between_balls(ball, other, ball.radius + other.radius, deceleration)
         {
-            earliest = Some(Collision {
-                time,
-                info: CollisionAgainst::Ball(ball_1.id, ball_2.id)
-            });
+            queue.push(time + t, CollisionAgainst::Ball(id, other.id));
         }
     }
 
-    for (ball, hole) in state.iter().cartesian_product(holes.iter()) {
-        if let Some(time) = find_collision_between_balls(ball, &Ball {
+    for hole in holes {
+        if let Some(t) = find_collision_between_balls(ball, &Ball {
             id: 0,
-            position: *hole,
+            position: hole.position,
             velocity: Vector2::zeros(),
             radius: 0.0,
-        }, config.ball_radius + config.hole_radius)
-            && earliest.map(|e| time < e.time).unwrap_or(true)
-        {
-            earliest = Some(Collision {
-                time,
-                info: CollisionAgainst::Hole(ball.id)
-            });
+            mass: 0.0,
+        }, ball.radius + hole.radius, deceleration) {
+            queue.push(time + t, CollisionAgainst::Hole(id));
         }
     }
 
-    for ball in state.iter() {
-        if let Some((time, wall_type)) = find_collision_against_wall(ball, config)
-            && earliest.map(|e| time < e.time).unwrap_or(true)
-        {
-            earliest = Some(Collision {
-                time,
-                info: CollisionAgainst::Wall(ball.id, wall_type)
-            });
+    if let Some((t, wall_type)) = find_collision_against_wall(ball, config, deceleration) {
+        queue.push(time + t, CollisionAgainst::Wall(id, wall_type));
+    }
+
+    for (i, cushion) in config.cushions.iter().enumerate() {
+        if let Some(t) = find_collision_against_cushion(ball, cushion, deceleration) {
+            queue.push(time + t, CollisionAgainst::Cushion(id, i));
         }
     }
 
-    earliest
+    if let Some((t, cell)) = grid.find_crossing(ball, deceleration) {
+        queue.push(time + t, CollisionAgainst::CellBoundary(id, cell));
+    }
+
+    let t = stop_time(ball, deceleration);
+    if t.is_finite() {
+        queue.push(time + t, CollisionAgainst::Rest(id));
+    }
 }
 
-fn apply_collision(state: &mut BTreeMap<ID, Ball>, config: &InputData, collision: Collision) {
+fn apply_collision(
+    state: &mut BTreeMap<ID, Ball>,
+    cushions: &[Cushion],
+    restitution: &Restitution,
+    collision: Collision,
+) -> Float {
     match collision.info {
         CollisionAgainst::Ball(id1, id2) => {
-            let delta_v = state[&id2].velocity - state[&id1].velocity;
-            let delta_r = state[&id2].position - state[&id1].position;
-            let sigma = config.ball_radius * 2.0;
-
-            let j = (2.0 * config.ball_mass.powi(2) * (delta_v.dot(&delta_r)))
-                / (sigma * (config.ball_mass * 2.0));
+            let (b1, b2) = (state[&id1], state[&id2]);
+            let delta_v = b2.velocity - b1.velocity;
+            let delta_r = b2.position - b1.position;
+            let sigma = b1.radius + b2.radius;
+            let e = restitution.between_balls(delta_v.dot(&delta_r) / sigma);
+
+            let j = ((1.0 + e) * b1.mass * b2.mass * (delta_v.dot(&delta_r)))
+                / (sigma * (b1.mass + b2.mass));
             let j_vec = delta_r * j / sigma;
 
             let ball_1 = state.get_mut(&id1).unwrap();
-            ball_1.velocity += j_vec / config.ball_mass;
+            ball_1.velocity += j_vec / b1.mass;
             let ball_2 = state.get_mut(&id2).unwrap();
-            ball_2.velocity -= j_vec / config.ball_mass;
+            ball_2.velocity -= j_vec / b2.mass;
+
+            j.abs()
+        }
+        CollisionAgainst::Wall(id, wall_type) => {
+            let ball = state.get_mut(&id).unwrap();
+            let normal_velocity = &mut ball.velocity[wall_type.axis()];
+            let e = restitution.against_wall(*normal_velocity);
+            let impulse = (1.0 + e) * ball.mass * normal_velocity.abs();
+            *normal_velocity *= -e;
+
+            impulse
+        }
+        CollisionAgainst::Cushion(id, cushion) => {
+            let ball = state.get_mut(&id).unwrap();
+            let contact = cushions[cushion].closest_point(&ball.position);
+            let normal = (ball.position - contact).normalize();
+            let normal_speed = ball.velocity.dot(&normal);
+            let e = restitution.against_wall(normal_speed);
+            ball.velocity -= normal * (1.0 + e) * normal_speed;
+
+            (1.0 + e) * ball.mass * normal_speed.abs()
         }
-        CollisionAgainst::Wall(id, wall_type) => match wall_type {
-            WallType::Horizontal => state.get_mut(&id).unwrap().velocity.y *= -1.0,
-            WallType::Vertical => state.get_mut(&id).unwrap().velocity.x *= -1.0,
-        },
         CollisionAgainst::Hole(id) => {
-            state.remove(&id);
+            let ball = state.remove(&id).unwrap();
+            ball.mass * ball.velocity.magnitude()
+        }
+        CollisionAgainst::Rest(id) => {
+            state.get_mut(&id).unwrap().velocity = Vector2::zeros();
+            0.0
         }
+        CollisionAgainst::CellBoundary(..) => 0.0,
     }
 }
 
-fn run<W: Write, F: FnMut(&BTreeMap<ID, Ball>, Float) -> bool>(
+/// Runs the simulation and returns the balls left on the table. With `end_time`, the balls are
+/// moved to exactly that time before returning.
+#[allow(clippy::too_many_arguments)]
+fn run<W: Write, E: Write, P: Write, F: FnMut(&BTreeMap<ID, Ball>, Float) -> bool>(
     config: InputData,
+    restitution: Restitution,
+    deceleration: Float,
+    end_time: Option<Float>,
     mut output_writer: W,
+    mut event_writer: Option<E>,
+    mut pressure: Option<(PressureAccumulator, P)>,
     mut stop_condition: F,
-) {
+) -> BTreeMap<ID, Ball> {
     let mut time = 0.0;
     let mut state: BTreeMap<_, _> = config.balls.iter().copied().map(|p| (p.id, p)).collect();
 
-    let holes = HOLE_POSITIONS
-        .map(|v| v.component_mul(&Vector2::new(config.table_width, config.table_height)));
-
     {
         // Write to output
         let frame = Frame {
@@ -181,15 +233,117 @@ fn run<W: Write, F: FnMut(&BTreeMap<ID, Ball>, Float) -> bool>(
         output_writer.write_fmt(format_args!("{frame}")).unwrap();
     }
 
-    while let Some(collision) = find_earliest_collision(&state.values().collect_vec(), &holes, &config) && !stop_condition(&state, time) {
+    if let Some(event_writer) = &mut event_writer {
+        event_writer
+            .write_fmt(format_args!("{EVENT_LOG_HEADER}\n"))
+            .unwrap();
+    }
+    if let Some((_, pressure_writer)) = &mut pressure {
+        pressure_writer
+            .write_fmt(format_args!("{PRESSURE_HEADER}\n"))
+            .unwrap();
+    }
+
+    let mut grid = CellGrid::new(
+        state.values(),
+        config.table_width,
+        config.table_height,
+        config.max_ball_radius() * 2.0,
+    );
+    let mut queue = EventQueue::new(state.keys().copied());
+    let ids = state.keys().copied().collect_vec();
+    for (i, &id) in ids.iter().enumerate() {
+        // Pairs with the previous balls were already scheduled.
+        predict_collisions(
+            id,
+            &state,
+            &grid,
+            &config.pockets,
+            &config,
+            deceleration,
+            time,
+            &ids[..i],
+            &mut queue,
+        );
+    }
+
+    while let Some(collision) = queue.pop() && !stop_condition(&state, time) {
+        if end_time.is_some_and(|end_time| collision.time > end_time) {
+            break;
+        }
+
         // Forward until earliest collision
         for ball in state.values_mut() {
-            ball.position += ball.velocity * collision.time;
+            advance(ball, collision.time - time, deceleration);
+        }
+
+        time = collision.time;
+
+        if let CollisionAgainst::CellBoundary(id, cell) = collision.info {
+            // The trajectory does not change, so the already scheduled collisions are still
+            // valid and only the balls of the new neighboring cells need to be checked.
+            grid.move_ball(id, cell);
+            predict_collisions(
+                id,
+                &state,
+                &grid,
+                &config.pockets,
+                &config,
+                deceleration,
+                time,
+                &[],
+                &mut queue,
+            );
+            continue;
+        }
+
+        let impulse = apply_collision(&mut state, &config.cushions, &restitution, collision);
+
+        if let Some(event_writer) = &mut event_writer {
+            let event = LoggedEvent {
+                time,
+                event: collision.info,
+                impulse,
+            };
+            event_writer.write_fmt(format_args!("{event}")).unwrap();
         }
 
-        time += collision.time;
+        if let Some((accumulator, pressure_writer)) = &mut pressure {
+            for window in accumulator.push(
+                time,
+                &collision.info,
+                impulse,
+                kinetic_energy(state.values()),
+                state.len(),
+            ) {
+                pressure_writer.write_fmt(format_args!("{window}")).unwrap();
+            }
+        }
 
-        apply_collision(&mut state, &config, collision);
+        let involved = collision.info.balls().collect_vec();
+        for &id in &involved {
+            if state.contains_key(&id) {
+                queue.invalidate(id);
+            } else {
+                queue.remove(id);
+                grid.remove(id);
+            }
+        }
+        for (i, &id) in involved.iter().enumerate() {
+            if state.contains_key(&id) {
+                predict_collisions(
+                    id,
+                    &state,
+                    &grid,
+                    &config.pockets,
+                    &config,
+                    deceleration,
+                    time,
+                    &involved[..i],
+                    &mut queue,
+                );
+            }
+        }
 
         // Write to output
         let frame = Frame {
@@ -198,25 +352,151 @@ fn run<W: Write, F: FnMut(&BTreeMap<ID, Ball>, Float) -> bool>(
         };
         output_writer.write_fmt(format_args!("{frame}")).unwrap();
     }
+
+    if let Some(end_time) = end_time && time < end_time {
+        for ball in state.values_mut() {
+            advance(ball, end_time - time, deceleration);
+        }
+        time = end_time;
+
+        let frame = Frame {
+            time,
+            balls: state.values().copied().collect_vec(),
+        };
+        output_writer.write_fmt(format_args!("{frame}")).unwrap();
+    }
+
+    if let Some((accumulator, mut pressure_writer)) = pressure
+        && let Some(window) = accumulator.finish(time)
+    {
+        pressure_writer.write_fmt(format_args!("{window}")).unwrap();
+    }
+
+    state
+}
+
+/// Distance of every ball to its initial position after running forward for `duration`,
+/// reversing the velocities and running back for the same duration.
+fn reversal_errors<W: Write>(
+    config: InputData,
+    restitution: Restitution,
+    deceleration: Float,
+    duration: Float,
+    output_writer: W,
+) -> Vec<Float> {
+    let config = InputData {
+        pockets: vec![],
+        ..config
+    };
+    let forward = run(
+        config.clone(),
+        restitution,
+        deceleration,
+        Some(duration),
+        output_writer,
+        None::<File>,
+        None::<(PressureAccumulator, File)>,
+        |_, _| false,
+    );
+
+    let reversed = InputData {
+        balls: forward
+            .values()
+            .map(|ball| Ball {
+                velocity: -ball.velocity,
+                ..*ball
+            })
+            .collect(),
+        ..config.clone()
+    };
+    let backward = run(
+        reversed,
+        restitution,
+        deceleration,
+        Some(duration),
+        io::sink(),
+        None::<File>,
+        None::<(PressureAccumulator, File)>,
+        |_, _| false,
+    );
+
+    config
+        .balls
+        .iter()
+        .map(|ball| (backward[&ball.id].position - ball.position).magnitude())
+        .collect()
 }
 
 fn main() {
     let args = Args::parse();
 
     let input = fs::read_to_string(args.input).unwrap();
-    let input = input_parser()
+    let mut input = input_parser()
         .parse(&input)
         .into_result()
         .expect("Error parsing input data.");
 
+    let table_height = input.table_height;
+    if let Some(white_ball) = input.balls.iter_mut().find(|b| b.id == WHITE_BALL_ID) {
+        if let Some(speed) = args.cue_speed {
+            let angle = args.cue_angle.to_radians();
+            white_ball.velocity = Vector2::new(angle.cos(), angle.sin()) * speed;
+        }
+        if let Some(offset) = args.cue_offset {
+            white_ball.position.y = table_height / 2.0 + offset;
+        }
+    }
+
     let writer = if let Some(output) = args.output {
         Box::new(File::create(output).unwrap()) as Box<dyn Write>
     } else {
         Box::new(stdout())
     };
 
-    run(input, writer, |_state, t| {
-        args.max_duration
-            .is_some_and(|max_duration| t > max_duration)
+    let restitution = Restitution {
+        ball: args.ball_restitution,
+        wall: args.wall_restitution,
+        threshold: args.restitution_threshold,
+    };
+
+    if let Some(duration) = args.reverse_at {
+        let errors = reversal_errors(
+            input,
+            restitution,
+            args.friction_deceleration,
+            duration,
+            writer,
+        );
+        let max_error = errors.iter().copied().fold(0.0, Float::max);
+        let rms_error =
+            (errors.iter().map(|e| e.powi(2)).sum::<Float>() / errors.len() as Float).sqrt();
+        eprintln!("max_error,rms_error\n{max_error},{rms_error}");
+        return;
+    }
+
+    let event_writer = args.event_log.map(|path| File::create(path).unwrap());
+    let pressure = args.pressure_output.map(|path| {
+        let accumulator = PressureAccumulator::new(
+            args.pressure_window,
+            input.table_width,
+            input.table_height,
+            kinetic_energy(&input.balls),
+            input.balls.len(),
+        );
+        (accumulator, File::create(path).unwrap())
     });
+
+    run(
+        input,
+        restitution,
+        args.friction_deceleration,
+        None,
+        writer,
+        event_writer,
+        pressure,
+        |_state, t| {
+            args.max_duration
+                .is_some_and(|max_duration| t > max_duration)
+        },
+    );
 }
@@ -4,19 +4,19 @@ use capturable_visualization::VisualizationBuilder;
 use chumsky::Parser;
 use clap::Parser as _parser;
 use itertools::Either;
-use nalgebra::Vector2;
 use nannou::prelude::*;
 use pool::{
     draw::draw as draw_pool,
-    models::{Ball, Frame, InputData},
+    models::{Frame, InputData},
     parser::{input_parser, output_parser},
-    Float, HOLE_POSITIONS,
+    Float,
 };
 use std::{
     fs::{read_to_string, File},
     io::{BufRead, BufReader},
     path::PathBuf,
 };
+use tp3::motion::advance;
 
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -29,6 +29,10 @@ struct Args {
 
     #[arg(long)]
     capture_directory: Option<PathBuf>,
+
+    /// Same deceleration given to the simulation, to interpolate the positions between frames
+    #[arg(long, default_value_t = 0.0)]
+    friction_deceleration: Float,
 }
 
 fn main() {
@@ -51,8 +55,8 @@ struct Model {
     frame_iter: Box<dyn Iterator<Item = Frame>>,
     frame: Frame,
     last_frame: Option<Frame>,
-    holes: Vec<Vector2<Float>>,
     time: Float,
+    deceleration: Float,
 }
 
 fn model(_app: &App, args: Args) -> Model {
@@ -65,13 +69,6 @@ fn model(_app: &App, args: Args) -> Model {
 
     let frame_iter = Box::new(output_parser(BufReader::new(output_file).lines()));
 
-    let holes = Vec::from(HOLE_POSITIONS.map(|v| {
-        v.component_mul(&Vector2::new(
-            system_info.table_width,
-            system_info.table_height,
-        ))
-    }));
-
     Model {
         last_frame: None,
         frame: Frame {
@@ -79,8 +76,8 @@ fn model(_app: &App, args: Args) -> Model {
             balls: system_info.balls.clone(),
         },
         time: 0.0,
+        deceleration: args.friction_deceleration,
         frame_iter,
-        holes,
         system_info,
     }
 }
@@ -95,27 +92,24 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
             time: Float::INFINITY,
             balls: model.last_frame.as_ref().unwrap().balls.clone(),
         });
+        model.system_info.restore_properties(&mut model.frame.balls);
     }
 }
 
 fn draw(_app: &App, model: &Model, draw: &Draw) {
     let interpolated_balls = if let Some(last_frame) = &model.last_frame {
-        Either::Left(last_frame.balls.iter().map(
-            |&Ball {
-                 id,
-                 position,
-                 velocity,
-                 radius,
-             }| Ball {
-                id,
-                position: position + velocity * (model.time - last_frame.time),
-                velocity,
-                radius,
-            },
-        ))
+        Either::Left(last_frame.balls.iter().copied().map(|mut ball| {
+            advance(&mut ball, model.time - last_frame.time, model.deceleration);
+            ball
+        }))
     } else {
         Either::Right(model.frame.balls.iter().cloned())
     };
 
-    draw_pool(&model.system_info, interpolated_balls, &model.holes, draw);
+    draw_pool(
+        &model.system_info,
+        interpolated_balls,
+        &model.system_info.pockets,
+        draw,
+    );
 }
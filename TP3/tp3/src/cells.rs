@@ -0,0 +1,131 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use cim::{cim_finder::SystemInfo, particles::ID};
+use nalgebra::Vector2;
+use pool::{models::Ball, Float};
+
+use crate::motion::travel_time;
+
+/// Grid of cells at least as large as a ball diameter, so a ball can only collide with the ones
+/// in its own or the adjacent cells until one of them crosses a cell boundary.
+pub struct CellGrid {
+    system: SystemInfo,
+    cells: BTreeMap<(usize, usize), BTreeSet<ID>>,
+    ball_cells: BTreeMap<ID, Vector2<usize>>,
+}
+
+impl CellGrid {
+    pub fn new<'a, I: IntoIterator<Item = &'a Ball>>(
+        balls: I,
+        width: Float,
+        height: Float,
+        min_cell_size: Float,
+    ) -> Self {
+        let columns = ((width / min_cell_size).floor() as usize).max(1);
+        let rows = ((height / min_cell_size).floor() as usize).max(1);
+        let size: Vector2<f64> = Vector2::new(width, height).cast();
+        let mut grid = Self {
+            system: SystemInfo {
+                cyclic: false,
+                interaction_radius: 0.0,
+                space_width: size.x,
+                space_height: size.y,
+                columns,
+                rows,
+            },
+            cells: BTreeMap::new(),
+            ball_cells: BTreeMap::new(),
+        };
+
+        for ball in balls {
+            grid.move_ball(ball.id, grid.cell_index(&ball.position));
+        }
+
+        grid
+    }
+
+    /// Same as `SystemInfo::cell_index`, but positions slightly outside the table because of
+    /// roundoff are assigned to the border cells.
+    fn cell_index(&self, position: &Vector2<Float>) -> Vector2<usize> {
+        let cell_size: Vector2<Float> = self.system.cell_size().cast();
+        self.clamp(position.component_div(&cell_size).map(|v| v.floor() as i64))
+    }
+
+    fn clamp(&self, cell: Vector2<i64>) -> Vector2<usize> {
+        Vector2::new(
+            cell.x.clamp(0, self.system.columns as i64 - 1) as usize,
+            cell.y.clamp(0, self.system.rows as i64 - 1) as usize,
+        )
+    }
+
+    pub fn cell_of(&self, id: ID) -> Vector2<usize> {
+        self.ball_cells[&id]
+    }
+
+    pub fn move_ball(&mut self, id: ID, cell: Vector2<usize>) {
+        self.remove(id);
+        self.cells.entry((cell.x, cell.y)).or_default().insert(id);
+        self.ball_cells.insert(id, cell);
+    }
+
+    pub fn remove(&mut self, id: ID) {
+        if let Some(balls) = self
+            .ball_cells
+            .remove(&id)
+            .and_then(|cell| self.cells.get_mut(&(cell.x, cell.y)))
+        {
+            balls.remove(&id);
+        }
+    }
+
+    /// Balls in the cell of the ball `id` and the ones around it, including itself.
+    pub fn neighbors(&self, id: ID) -> impl Iterator<Item = ID> + '_ {
+        let cell = self.cell_of(id).cast::<i64>();
+        (-1..=1)
+            .flat_map(move |dx| (-1..=1).map(move |dy| cell + Vector2::new(dx, dy)))
+            .filter(|other| {
+                other.x >= 0
+                    && other.y >= 0
+                    && (other.x as usize) < self.system.columns
+                    && (other.y as usize) < self.system.rows
+            })
+            .filter_map(|other| self.cells.get(&(other.x as usize, other.y as usize)))
+            .flatten()
+            .copied()
+    }
+
+    /// Time until the ball leaves its cell and the cell it enters.
+    pub fn find_crossing(
+        &self,
+        ball: &Ball,
+        deceleration: Float,
+    ) -> Option<(Float, Vector2<usize>)> {
+        let cell = self.cell_of(ball.id);
+        let cell_size: Vector2<Float> = self.system.cell_size().cast();
+        let lower = cell.cast::<Float>().component_mul(&cell_size);
+        let upper = lower + cell_size;
+
+        (0..2)
+            .filter_map(|axis| {
+                let mut step = Vector2::zeros();
+                let time = if ball.velocity[axis] > 0.0 {
+                    step[axis] = 1;
+                    let distance = upper[axis] - ball.position[axis];
+                    travel_time(ball, &Vector2::ith(axis, 1.0), distance, deceleration)?
+                } else if ball.velocity[axis] < 0.0 {
+                    step[axis] = -1;
+                    let distance = ball.position[axis] - lower[axis];
+                    travel_time(ball, &Vector2::ith(axis, -1.0), distance, deceleration)?
+                } else {
+                    return None;
+                };
+                let next = cell.cast::<i64>() + step;
+                let inside = next.x >= 0
+                    && next.y >= 0
+                    && (next.x as usize) < self.system.columns
+                    && (next.y as usize) < self.system.rows;
+                inside.then(|| (time.max(0.0), self.clamp(next)))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
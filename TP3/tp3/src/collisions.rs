@@ -0,0 +1,216 @@
+use cim::particles::ID;
+use nalgebra::Vector2;
+use pool::{
+    models::{Ball, Cushion, InputData},
+    Float,
+};
+
+use crate::{
+    motion::{acceleration, advance, stop_time, travel_time},
+    polynomial::Polynomial,
+};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Collision {
+    pub time: Float,
+    pub info: CollisionAgainst,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum WallType {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl WallType {
+    /// Axis of the velocity component the wall reflects.
+    pub fn axis(&self) -> usize {
+        match self {
+            WallType::Left | WallType::Right => 0,
+            WallType::Bottom | WallType::Top => 1,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum CollisionAgainst {
+    Ball(ID, ID),
+    Wall(ID, WallType),
+    Hole(ID),
+    /// Collision against the cushion with the given index.
+    Cushion(ID, usize),
+    /// Not a real collision, the ball stops because of the friction with the cloth.
+    Rest(ID),
+    /// Not a real collision, the ball moves to another cell of the grid.
+    CellBoundary(ID, Vector2<usize>),
+}
+
+impl CollisionAgainst {
+    /// Balls whose trajectory changes with this collision.
+    pub fn balls(&self) -> impl Iterator<Item = ID> {
+        match *self {
+            CollisionAgainst::Ball(id1, id2) => [Some(id1), Some(id2)],
+            CollisionAgainst::Wall(id, _)
+            | CollisionAgainst::Hole(id)
+            | CollisionAgainst::Cushion(id, _)
+            | CollisionAgainst::Rest(id)
+            | CollisionAgainst::CellBoundary(id, _) => [Some(id), None],
+        }
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Normal restitution coefficients of the contacts. Contacts with a normal speed below
+/// `threshold` are elastic, which keeps clusters of balls from undergoing inelastic collapse.
+#[derive(Debug, Copy, Clone)]
+pub struct Restitution {
+    pub ball: Float,
+    pub wall: Float,
+    pub threshold: Float,
+}
+
+impl Restitution {
+    fn coefficient(&self, e: Float, normal_speed: Float) -> Float {
+        if normal_speed.abs() < self.threshold {
+            1.0
+        } else {
+            e
+        }
+    }
+
+    pub fn between_balls(&self, normal_speed: Float) -> Float {
+        self.coefficient(self.ball, normal_speed)
+    }
+
+    pub fn against_wall(&self, normal_speed: Float) -> Float {
+        self.coefficient(self.wall, normal_speed)
+    }
+}
+
+pub fn find_collision_between_balls(
+    b1: &Ball,
+    b2: &Ball,
+    radius_sum: Float,
+    deceleration: Float,
+) -> Option<Float> {
+    if deceleration > 0.0 {
+        return find_collision_with_friction(b1, b2, radius_sum, deceleration);
+    }
+
+    let delta_v = b2.velocity - b1.velocity;
+    let delta_r = b2.position - b1.position;
+    let sigma = radius_sum;
+    let d = (delta_v.dot(&delta_r).powi(2))
+        - delta_v.dot(&delta_v) * (delta_r.dot(&delta_r) - sigma.powi(2));
+
+    (delta_v.dot(&delta_r) < 0.0 && d >= 0.0)
+        .then(|| -(delta_v.dot(&delta_r) + d.sqrt()) / (delta_v.dot(&delta_v)))
+}
+
+/// With friction the distance between the balls is a quartic in time, which is only valid until
+/// one of them stops.
+fn find_collision_with_friction(
+    b1: &Ball,
+    b2: &Ball,
+    sigma: Float,
+    deceleration: Float,
+) -> Option<Float> {
+    let delta_v = b2.velocity - b1.velocity;
+    let delta_r = b2.position - b1.position;
+    let delta_a = acceleration(b2, deceleration) - acceleration(b1, deceleration);
+    let end = stop_time(b1, deceleration).min(stop_time(b2, deceleration));
+
+    if !end.is_finite() {
+        return None;
+    }
+
+    // |delta_r + delta_v t + delta_a t^2 / 2|^2 - sigma^2
+    Polynomial(vec![
+        delta_r.dot(&delta_r) - sigma.powi(2),
+        2.0 * delta_r.dot(&delta_v),
+        delta_v.dot(&delta_v) + delta_r.dot(&delta_a),
+        delta_v.dot(&delta_a),
+        delta_a.dot(&delta_a) / 4.0,
+    ])
+    .first_descending_root(0.0, end)
+}
+
+pub fn find_collision_against_wall(
+    ball: &Ball,
+    config: &InputData,
+    deceleration: Float,
+) -> Option<(Float, WallType)> {
+    let radius = ball.radius;
+
+    let time_x = if ball.velocity.x > 0.0 {
+        let distance = config.table_width - radius - ball.position.x;
+        travel_time(ball, &Vector2::x(), distance, deceleration).map(|t| (t, WallType::Right))
+    } else if ball.velocity.x < 0.0 {
+        let distance = ball.position.x - radius;
+        travel_time(ball, &-Vector2::x(), distance, deceleration).map(|t| (t, WallType::Left))
+    } else {
+        None
+    };
+
+    let time_y = if ball.velocity.y > 0.0 {
+        let distance = config.table_height - radius - ball.position.y;
+        travel_time(ball, &Vector2::y(), distance, deceleration).map(|t| (t, WallType::Top))
+    } else if ball.velocity.y < 0.0 {
+        let distance = ball.position.y - radius;
+        travel_time(ball, &-Vector2::y(), distance, deceleration).map(|t| (t, WallType::Bottom))
+    } else {
+        None
+    };
+
+    time_x
+        .into_iter()
+        .chain(time_y)
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+}
+
+/// The ball can hit the straight part of the cushion or one of its ends, which behave like a
+/// ball of radius zero.
+pub fn find_collision_against_cushion(
+    ball: &Ball,
+    cushion: &Cushion,
+    deceleration: Float,
+) -> Option<Float> {
+    let direction = cushion.end - cushion.start;
+    let length = direction.magnitude();
+    let tangent = direction / length;
+    let mut normal = Vector2::new(-tangent.y, tangent.x);
+    let mut distance = normal.dot(&(ball.position - cushion.start));
+    if distance < 0.0 {
+        normal = -normal;
+        distance = -distance;
+    }
+
+    // A ball closer to the line than its radius is beyond the ends, so it can only hit them.
+    let side_time = (distance >= ball.radius)
+        .then(|| travel_time(ball, &-normal, distance - ball.radius, deceleration))
+        .flatten()
+        .filter(|&t| {
+            let mut ball = *ball;
+            advance(&mut ball, t, deceleration);
+            (0.0..=length).contains(&tangent.dot(&(ball.position - cushion.start)))
+        });
+
+    let end_times = [cushion.start, cushion.end].map(|position| {
+        let end = Ball {
+            id: 0,
+            position,
+            velocity: Vector2::zeros(),
+            radius: 0.0,
+            mass: 0.0,
+        };
+        find_collision_between_balls(ball, &end, ball.radius, deceleration)
+    });
+
+    side_time
+        .into_iter()
+        .chain(end_times.into_iter().flatten())
+        .min_by(|a, b| a.total_cmp(b))
+}
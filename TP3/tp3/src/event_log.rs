@@ -0,0 +1,83 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Lines},
+};
+
+use pool::Float;
+
+use crate::collisions::{CollisionAgainst, WallType};
+
+pub const EVENT_LOG_HEADER: &str = "time,type,id1,id2,impulse";
+
+/// Event as written to the event log. Cell boundary crossings are not logged.
+#[derive(Debug, Clone, Copy)]
+pub struct LoggedEvent {
+    pub time: Float,
+    pub event: CollisionAgainst,
+    /// Momentum the event takes from the first ball: the impulse of a collision, or all of it
+    /// when it falls into a hole.
+    pub impulse: Float,
+}
+
+fn event_type(event: &CollisionAgainst) -> &'static str {
+    match event {
+        CollisionAgainst::Ball(..) => "ball",
+        CollisionAgainst::Wall(_, WallType::Left) => "wall_left",
+        CollisionAgainst::Wall(_, WallType::Right) => "wall_right",
+        CollisionAgainst::Wall(_, WallType::Bottom) => "wall_bottom",
+        CollisionAgainst::Wall(_, WallType::Top) => "wall_top",
+        CollisionAgainst::Cushion(..) => "cushion",
+        CollisionAgainst::Hole(_) => "hole",
+        CollisionAgainst::Rest(_) => "rest",
+        CollisionAgainst::CellBoundary(..) => "cell",
+    }
+}
+
+impl Display for LoggedEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut balls = self.event.balls();
+        let id1 = balls.next().unwrap();
+        // Cushion collisions write the index of the cushion instead of a second ball.
+        let id2 = match self.event {
+            CollisionAgainst::Cushion(_, cushion) => Some(cushion),
+            _ => balls.next(),
+        }
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+        f.write_fmt(format_args!(
+            "{},{},{id1},{id2},{}\n",
+            self.time,
+            event_type(&self.event),
+            self.impulse
+        ))
+    }
+}
+
+fn parse_line(line: &str) -> LoggedEvent {
+    let [time, event_type, id1, id2, impulse]: [&str; 5] =
+        line.split(',').collect::<Vec<_>>().try_into().unwrap();
+    let id1 = id1.parse().unwrap();
+    let event = match event_type {
+        "ball" => CollisionAgainst::Ball(id1, id2.parse().unwrap()),
+        "wall_left" => CollisionAgainst::Wall(id1, WallType::Left),
+        "wall_right" => CollisionAgainst::Wall(id1, WallType::Right),
+        "wall_bottom" => CollisionAgainst::Wall(id1, WallType::Bottom),
+        "wall_top" => CollisionAgainst::Wall(id1, WallType::Top),
+        "cushion" => CollisionAgainst::Cushion(id1, id2.parse().unwrap()),
+        "hole" => CollisionAgainst::Hole(id1),
+        "rest" => CollisionAgainst::Rest(id1),
+        _ => panic!("Unknown event type {event_type}."),
+    };
+
+    LoggedEvent {
+        time: time.parse().unwrap(),
+        event,
+        impulse: impulse.parse().unwrap(),
+    }
+}
+
+pub fn event_log_parser<B: BufRead>(file: Lines<B>) -> impl Iterator<Item = LoggedEvent> {
+    file.map(Result::unwrap)
+        .filter(|line| line != EVENT_LOG_HEADER)
+        .map(|line| parse_line(&line))
+}
@@ -0,0 +1,109 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BinaryHeap},
+};
+
+use cim::particles::ID;
+use pool::Float;
+
+use crate::collisions::{Collision, CollisionAgainst};
+
+#[derive(Debug)]
+struct ScheduledCollision {
+    collision: Collision,
+    /// Collision counts of the involved balls when the collision was predicted.
+    counts: [Option<u64>; 2],
+}
+
+impl PartialEq for ScheduledCollision {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScheduledCollision {}
+
+impl PartialOrd for ScheduledCollision {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledCollision {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.collision.time.total_cmp(&other.collision.time)
+    }
+}
+
+/// Predicted collisions ordered by time. Every ball carries a collision counter, so collisions
+/// predicted before any of their balls changed trajectory are discarded when popped.
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    heap: BinaryHeap<Reverse<ScheduledCollision>>,
+    collision_counts: BTreeMap<ID, u64>,
+}
+
+impl EventQueue {
+    pub fn new<I: IntoIterator<Item = ID>>(balls: I) -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            collision_counts: balls.into_iter().map(|id| (id, 0)).collect(),
+        }
+    }
+
+    fn counts(&self, info: &CollisionAgainst) -> [Option<u64>; 2] {
+        let mut counts = [None; 2];
+        for (count, id) in counts.iter_mut().zip(info.balls()) {
+            *count = self.collision_counts.get(&id).copied();
+        }
+        counts
+    }
+
+    /// Schedules a collision happening at the absolute time `time`.
+    pub fn push(&mut self, time: Float, info: CollisionAgainst) {
+        let counts = self.counts(&info);
+        self.heap.push(Reverse(ScheduledCollision {
+            collision: Collision { time, info },
+            counts,
+        }));
+    }
+
+    /// Earliest collision that is still valid.
+    pub fn pop(&mut self) -> Option<Collision> {
+        while let Some(Reverse(scheduled)) = self.heap.pop() {
+            let is_valid =
+                scheduled
+                    .collision
+                    .info
+                    .balls()
+                    .zip(scheduled.counts)
+                    .all(|(id, count)| {
+                        count.is_some() && self.collision_counts.get(&id).copied() == count
+                    });
+            if is_valid {
+                return Some(scheduled.collision);
+            }
+        }
+        None
+    }
+
+    /// Discards every scheduled collision involving the ball.
+    pub fn invalidate(&mut self, id: ID) {
+        if let Some(count) = self.collision_counts.get_mut(&id) {
+            *count += 1;
+        }
+    }
+
+    /// Discards every scheduled collision involving the ball and stops tracking it.
+    pub fn remove(&mut self, id: ID) {
+        self.collision_counts.remove(&id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
@@ -0,0 +1,7 @@
+pub mod cells;
+pub mod collisions;
+pub mod event_log;
+pub mod events;
+pub mod motion;
+pub mod polynomial;
+pub mod pressure;
@@ -0,0 +1,53 @@
+use nalgebra::Vector2;
+use pool::{models::Ball, Float};
+
+/// Constant deceleration from the friction with the cloth, opposite to the velocity, so the
+/// balls move in a straight line until they stop.
+pub fn acceleration(ball: &Ball, deceleration: Float) -> Vector2<Float> {
+    let speed = ball.velocity.magnitude();
+    if speed > 0.0 {
+        -ball.velocity * deceleration / speed
+    } else {
+        Vector2::zeros()
+    }
+}
+
+/// Time until the ball comes to rest, infinite without friction or if it is already at rest.
+pub fn stop_time(ball: &Ball, deceleration: Float) -> Float {
+    let speed = ball.velocity.magnitude();
+    if deceleration > 0.0 && speed > 0.0 {
+        speed / deceleration
+    } else {
+        Float::INFINITY
+    }
+}
+
+/// Moves the ball `dt` forward. If it stops before that, it stays at rest.
+pub fn advance(ball: &mut Ball, dt: Float, deceleration: Float) {
+    let acceleration = acceleration(ball, deceleration);
+    let stop_time = stop_time(ball, deceleration);
+    let t = dt.min(stop_time);
+
+    ball.position += ball.velocity * t + acceleration * t.powi(2) / 2.0;
+    if dt >= stop_time {
+        ball.velocity = Vector2::zeros();
+    } else {
+        ball.velocity += acceleration * t;
+    }
+}
+
+/// Time until the ball moves `distance` along the unit vector `direction`, if it is moving that
+/// way and does not stop before.
+pub fn travel_time(
+    ball: &Ball,
+    direction: &Vector2<Float>,
+    distance: Float,
+    deceleration: Float,
+) -> Option<Float> {
+    let speed = ball.velocity.dot(direction);
+    let deceleration = -acceleration(ball, deceleration).dot(direction);
+    let discriminant = speed.powi(2) - 2.0 * deceleration * distance;
+
+    // Same as (speed - sqrt(discriminant)) / deceleration, but it also works without friction.
+    (speed > 0.0 && discriminant >= 0.0).then(|| 2.0 * distance / (speed + discriminant.sqrt()))
+}
@@ -0,0 +1,81 @@
+use pool::Float;
+
+/// Polynomial with its coefficients sorted by increasing degree.
+#[derive(Debug, Clone)]
+pub struct Polynomial(pub Vec<Float>);
+
+impl Polynomial {
+    pub fn evaluate(&self, t: Float) -> Float {
+        self.0.iter().rev().fold(0.0, |value, c| value * t + c)
+    }
+
+    pub fn derivative(&self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(degree, c)| degree as Float * c)
+                .collect(),
+        )
+    }
+
+    /// `start`, the critical points between `start` and `end`, and `end`, so the polynomial is
+    /// monotonic between every consecutive pair.
+    fn monotonic_intervals(&self, start: Float, end: Float) -> Vec<Float> {
+        let mut points = vec![start];
+        points.extend(self.derivative().roots(start, end));
+        points.push(end);
+        points
+    }
+
+    /// Roots between `start` and `end` where the polynomial changes sign, in increasing order.
+    pub fn roots(&self, start: Float, end: Float) -> Vec<Float> {
+        if self.0.len() <= 1 {
+            return vec![];
+        }
+
+        self.monotonic_intervals(start, end)
+            .windows(2)
+            .filter_map(|interval| self.bisect(interval[0], interval[1]))
+            .collect()
+    }
+
+    /// Earliest time between `start` and `end` where the polynomial is decreasing and not
+    /// positive.
+    pub fn first_descending_root(&self, start: Float, end: Float) -> Option<Float> {
+        self.monotonic_intervals(start, end)
+            .windows(2)
+            .find_map(|interval| {
+                let (a, b) = (interval[0], interval[1]);
+                let (value_a, value_b) = (self.evaluate(a), self.evaluate(b));
+                if value_b >= value_a || value_b > 0.0 {
+                    None
+                } else if value_a <= 0.0 {
+                    Some(a)
+                } else {
+                    self.bisect(a, b)
+                }
+            })
+    }
+
+    /// Root of a monotonic interval, if the polynomial changes sign in it.
+    fn bisect(&self, mut a: Float, mut b: Float) -> Option<Float> {
+        let is_positive_a = self.evaluate(a) > 0.0;
+        if is_positive_a == (self.evaluate(b) > 0.0) {
+            return None;
+        }
+
+        loop {
+            let middle = (a + b) / 2.0;
+            if middle <= a || middle >= b {
+                return Some(middle);
+            }
+            if (self.evaluate(middle) > 0.0) == is_positive_a {
+                a = middle;
+            } else {
+                b = middle;
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt::Display;
+
+use pool::{models::Ball, Float};
+
+use crate::collisions::CollisionAgainst;
+
+pub const PRESSURE_HEADER: &str =
+    "start,end,left,right,bottom,top,pressure,kinetic_energy,temperature";
+
+/// Pressure on every wall and the mean pressure over a time window, along with the time averaged
+/// kinetic energy and kinetic temperature kT = E / N of the balls, so PA = NkT can be checked.
+#[derive(Debug, Clone, Copy)]
+pub struct PressureWindow {
+    pub start: Float,
+    pub end: Float,
+    /// Indexed like `WallType`: left, right, bottom and top.
+    pub walls: [Float; 4],
+    pub pressure: Float,
+    pub kinetic_energy: Float,
+    pub temperature: Float,
+}
+
+impl Display for PressureWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [left, right, bottom, top] = self.walls;
+        f.write_fmt(format_args!(
+            "{},{},{left},{right},{bottom},{top},{},{},{}\n",
+            self.start, self.end, self.pressure, self.kinetic_energy, self.temperature
+        ))
+    }
+}
+
+/// Parses the duration of the pressure windows, which must be positive for the windows to
+/// advance.
+pub fn parse_window(s: &str) -> Result<Float, String> {
+    let window: Float = s.parse().map_err(|e| format!("{e}"))?;
+    if window > 0.0 {
+        Ok(window)
+    } else {
+        Err(format!("{window} is not a positive duration"))
+    }
+}
+
+fn temperature(kinetic_energy: Float, ball_count: usize) -> Float {
+    if ball_count > 0 {
+        kinetic_energy / ball_count as Float
+    } else {
+        0.0
+    }
+}
+
+/// Accumulates the momentum transferred to the walls in consecutive time windows.
+#[derive(Debug)]
+pub struct PressureAccumulator {
+    window: Float,
+    wall_lengths: [Float; 4],
+    start: Float,
+    impulses: [Float; 4],
+    /// Time integrals of the kinetic energy and the temperature since `start`.
+    energy_integral: Float,
+    temperature_integral: Float,
+    /// Time of the last event, with the kinetic energy and temperature after it.
+    last: (Float, Float, Float),
+}
+
+impl PressureAccumulator {
+    /// `kinetic_energy` is the one of the `ball_count` balls at the start.
+    pub fn new(
+        window: Float,
+        table_width: Float,
+        table_height: Float,
+        kinetic_energy: Float,
+        ball_count: usize,
+    ) -> Self {
+        assert!(window > 0.0, "The pressure window must be positive.");
+        Self {
+            window,
+            wall_lengths: [table_height, table_height, table_width, table_width],
+            start: 0.0,
+            impulses: [0.0; 4],
+            energy_integral: 0.0,
+            temperature_integral: 0.0,
+            last: (0.0, kinetic_energy, temperature(kinetic_energy, ball_count)),
+        }
+    }
+
+    fn integrate_until(&mut self, time: Float) {
+        let (last_time, energy, temperature) = self.last;
+        self.energy_integral += energy * (time - last_time);
+        self.temperature_integral += temperature * (time - last_time);
+        self.last.0 = time;
+    }
+
+    fn close_window(&mut self, end: Float) -> PressureWindow {
+        self.integrate_until(end);
+        let duration = end - self.start;
+        let walls = [0, 1, 2, 3].map(|i| self.impulses[i] / (duration * self.wall_lengths[i]));
+        let window = PressureWindow {
+            start: self.start,
+            end,
+            walls,
+            pressure: self.impulses.iter().sum::<Float>()
+                / (duration * self.wall_lengths.iter().sum::<Float>()),
+            kinetic_energy: self.energy_integral / duration,
+            temperature: self.temperature_integral / duration,
+        };
+
+        self.start = end;
+        self.impulses = [0.0; 4];
+        self.energy_integral = 0.0;
+        self.temperature_integral = 0.0;
+        window
+    }
+
+    /// Adds an event, which must come in time order, along with the kinetic energy of the
+    /// `ball_count` balls after it. Returns the windows that ended before the event.
+    pub fn push(
+        &mut self,
+        time: Float,
+        event: &CollisionAgainst,
+        impulse: Float,
+        kinetic_energy: Float,
+        ball_count: usize,
+    ) -> Vec<PressureWindow> {
+        let mut windows = vec![];
+        while time >= self.start + self.window {
+            windows.push(self.close_window(self.start + self.window));
+        }
+
+        self.integrate_until(time);
+        if let CollisionAgainst::Wall(_, wall) = *event {
+            self.impulses[wall as usize] += impulse;
+        }
+        self.last = (
+            time,
+            kinetic_energy,
+            temperature(kinetic_energy, ball_count),
+        );
+
+        windows
+    }
+
+    /// Last window, which may be shorter than the others.
+    pub fn finish(mut self, time: Float) -> Option<PressureWindow> {
+        (time > self.start).then(|| self.close_window(time))
+    }
+}
+
+pub fn kinetic_energy<'a, I: IntoIterator<Item = &'a Ball>>(balls: I) -> Float {
+    balls
+        .into_iter()
+        .map(|ball| 0.5 * ball.mass * ball.velocity.magnitude_squared())
+        .sum()
+}
@@ -1,124 +1,571 @@
 #![feature(trait_alias)]
 
-use crate::{beeman::beeman, gear_predictor_corrector::gear_predictor_corrector, verlet::verlet};
-use clap::{Parser, Subcommand};
+use std::{
+    f64::consts::PI,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::Instant,
+};
 
-mod beeman;
-mod gear_predictor_corrector;
-mod verlet;
+use clap::{builder::RangedU64ValueParser, Parser, Subcommand};
+use integrators::{
+    Beeman, DormandPrince, Euler, ExtrapolatedBeeman, Gear, GearCoefficients, GearInitialization,
+    Integrator, Leapfrog, PositionVerlet, RungeKutta4, Tolerances, VelocityVerlet,
+};
+use itertools::Itertools;
+use problems::{
+    Duffing, Kepler, LennardJonesDimer, Oscillator, Pendulum, Problem, ProblemKind, Reference,
+};
 
-trait CallbackFn = FnMut(f64, f64, f64);
+mod problems;
 
-pub fn euler_algorithm(r: f64, v: f64, f: f64, dt: f64, m: f64) -> (f64, f64) {
-    let next_r = r + dt * v + (dt.powi(2) / (2.0 * m)) * f;
+trait CallbackFn = FnMut(f64, &[f64], &[f64]);
 
-    let next_v = v + (dt / m) * f;
-
-    (next_r, next_v)
-}
+/// Calls back with the reference solution of `problem` every `dt` until `tf`.
+fn reference<Callback: CallbackFn>(
+    problem: &dyn Problem,
+    dt: f64,
+    tf: f64,
+    mut callback: Callback,
+) {
+    let mut reference = Reference::new(problem);
+    let mut t = 0.0;
 
-fn analytic_solution(a: f64, gamma: f64, m: f64, t: f64, k: f64) -> f64 {
-    let beta = gamma / (2.0 * m);
-    let omega = ((k / m) - beta.powi(2)).sqrt();
+    while t < tf {
+        let (curr_r, curr_v) = reference.state(t);
+        callback(t, &curr_r, &curr_v);
+        t += dt;
+    }
 
-    a * (-beta * t).exp() * (omega * t).cos()
+    let (r, v) = reference.state(t);
+    callback(t, &r, &v);
 }
 
-pub(crate) fn analytic<F: Fn(f64) -> f64, Callback: CallbackFn>(
-    analytic_solution: F,
+/// Integrates `problem` with steps of `dt` until `tf`, calling back with the state before every
+/// step. Returns the number of evaluations of the acceleration.
+fn integrate<Callback: CallbackFn>(
+    integrator: &mut dyn Integrator<f64, f64>,
+    problem: &dyn Problem,
     dt: f64,
+    tf: f64,
     mut callback: Callback,
-) {
+) -> usize {
     let mut t = 0.0;
-    let tf = 5.0;
+
+    let (mut positions, mut velocities) = problem.initial_state();
+    let mut evaluations = 0;
+    let mut acceleration = |t: f64, r: &[f64], v: &[f64], a: &mut [f64]| {
+        evaluations += 1;
+        problem.acceleration(t, r, v, a);
+    };
 
     while t < tf {
-        let curr_r = analytic_solution(t);
-        callback(t, curr_r, 0.0);
+        callback(t, &positions, &velocities);
+
+        integrator.step(t, dt, &mut positions, &mut velocities, &mut acceleration);
+
         t += dt;
     }
 
-    callback(t, analytic_solution(t), 0.0);
+    callback(t, &positions, &velocities);
+
+    evaluations
+}
+
+/// Gear method with `N` terms, started from the exact derivatives of the problem when it has
+/// them and no `initialization` is chosen, or from a bootstrap otherwise.
+fn gear<const N: usize>(
+    problem: &dyn Problem,
+    coefficients: GearCoefficients,
+    initialization: Option<GearInitialization>,
+) -> Box<dyn Integrator<f64, f64>> {
+    match (initialization, problem.initial_derivatives()) {
+        (None, Some(mut derivatives)) => {
+            for coordinate in &mut derivatives {
+                coordinate.truncate(N - 2);
+            }
+            Box::new(Gear::<_, N>::with_derivatives(coefficients, derivatives))
+        }
+        (initialization, _) => Box::new(Gear::<_, N>::new(
+            coefficients,
+            initialization.unwrap_or(GearInitialization::Bootstrap),
+        )),
+    }
 }
 
 #[derive(Subcommand, Default, Debug, Clone)]
 enum Method {
+    /// The analytic solution, or a Dormand-Prince run with tight tolerances for the problems
+    /// without one
     #[default]
     Analytic,
-    Gear,
+    Euler,
+    Gear {
+        /// Highest derivative of the position kept, from 2 to 5
+        #[clap(
+            long,
+            default_value_t = 5,
+            value_parser = RangedU64ValueParser::<usize>::new().range(2..=5)
+        )]
+        order: usize,
+
+        /// Corrector coefficients, `position` or `velocity`. Defaults to the ones for the forces
+        /// of the problem.
+        #[clap(long)]
+        coefficients: Option<GearCoefficients>,
+
+        /// Estimation of the derivatives above the acceleration, `zero`, `finite-differences` or
+        /// `bootstrap`. Defaults to the exact ones of the problem, or to `bootstrap` for the
+        /// problems without them.
+        #[clap(long)]
+        initialization: Option<GearInitialization>,
+    },
     Verlet,
-    Beeman,
+    VelocityVerlet,
+    Leapfrog,
+    /// Beeman predictor-corrector, evaluating the forces at the predicted velocity
+    Beeman {
+        /// Evaluations of the forces at the end of every step, each one correcting the velocity
+        /// again
+        #[clap(
+            long,
+            default_value_t = 1,
+            value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        corrector_iterations: usize,
+
+        /// Change of the velocity below which the corrections stop early
+        #[clap(long, default_value_t = 0.0)]
+        tolerance: f64,
+    },
+    /// Beeman with the acceleration at the end of the step extrapolated from the previous ones,
+    /// which never corrects the damping
+    BeemanExtrapolated,
+    Rk4,
+    /// Adaptive Dormand-Prince 5(4), interpolated at every step of the output
+    DormandPrince {
+        #[clap(long, default_value_t = 1e-8)]
+        absolute_tolerance: f64,
+
+        #[clap(long, default_value_t = 1e-8)]
+        relative_tolerance: f64,
+    },
 }
 
+impl Method {
+    /// Every method whose error depends on the step.
+    fn fixed_step() -> Vec<Method> {
+        vec![
+            Method::Euler,
+            Method::Verlet,
+            Method::VelocityVerlet,
+            Method::Leapfrog,
+            Method::Beeman {
+                corrector_iterations: 1,
+                tolerance: 0.0,
+            },
+            Method::BeemanExtrapolated,
+            Method::Rk4,
+            Method::Gear {
+                order: 2,
+                coefficients: None,
+                initialization: None,
+            },
+            Method::Gear {
+                order: 3,
+                coefficients: None,
+                initialization: None,
+            },
+            Method::Gear {
+                order: 4,
+                coefficients: None,
+                initialization: None,
+            },
+            Method::Gear {
+                order: 5,
+                coefficients: None,
+                initialization: None,
+            },
+        ]
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Method::Analytic => "analytic".to_string(),
+            Method::Euler => "euler".to_string(),
+            Method::Gear {
+                order,
+                coefficients: None,
+                ..
+            } => format!("gear-{order}"),
+            Method::Gear {
+                order,
+                coefficients: Some(coefficients),
+                ..
+            } => format!("gear-{order}-{coefficients}"),
+            Method::Verlet => "verlet".to_string(),
+            Method::VelocityVerlet => "velocity-verlet".to_string(),
+            Method::Leapfrog => "leapfrog".to_string(),
+            Method::Beeman { .. } => "beeman".to_string(),
+            Method::BeemanExtrapolated => "beeman-extrapolated".to_string(),
+            Method::Rk4 => "rk4".to_string(),
+            Method::DormandPrince { .. } => "dormand-prince".to_string(),
+        }
+    }
+
+    fn integrator(&self, problem: &dyn Problem) -> Option<Box<dyn Integrator<f64, f64>>> {
+        match *self {
+            Method::Analytic => None,
+            Method::Euler => Some(Box::<Euler<_>>::default()),
+            Method::Gear {
+                order,
+                coefficients,
+                initialization,
+            } => {
+                let coefficients = coefficients.unwrap_or(if problem.velocity_dependent() {
+                    GearCoefficients::Velocity
+                } else {
+                    GearCoefficients::Position
+                });
+                Some(match order {
+                    2 => gear::<3>(problem, coefficients, initialization),
+                    3 => gear::<4>(problem, coefficients, initialization),
+                    4 => gear::<5>(problem, coefficients, initialization),
+                    5 => gear::<6>(problem, coefficients, initialization),
+                    _ => unreachable!(),
+                })
+            }
+            Method::Verlet => Some(Box::<PositionVerlet<_>>::default()),
+            Method::VelocityVerlet => Some(Box::<VelocityVerlet<_>>::default()),
+            Method::Leapfrog => Some(Box::<Leapfrog<_>>::default()),
+            Method::Beeman {
+                corrector_iterations,
+                tolerance,
+            } => Some(Box::new(Beeman::new(corrector_iterations, tolerance))),
+            Method::BeemanExtrapolated => Some(Box::<ExtrapolatedBeeman<_>>::default()),
+            Method::Rk4 => Some(Box::<RungeKutta4<_>>::default()),
+            Method::DormandPrince {
+                absolute_tolerance,
+                relative_tolerance,
+            } => Some(Box::new(DormandPrince::new(Tolerances {
+                absolute: absolute_tolerance,
+                relative: relative_tolerance,
+            }))),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    #[clap(flatten)]
+    Method(Method),
+    /// Runs every fixed step method with several steps and prints the MSE, maximum error and
+    /// wall time of every run against the reference solution. The orders of accuracy fitted
+    /// from the errors are printed to stderr.
+    Convergence {
+        /// Exponents of the steps, dt = 10^-n
+        #[clap(long, value_delimiter = ',', default_values_t = [2, 3, 4, 5])]
+        delta_t_exponents: Vec<i32>,
+    },
+}
+
+/// Integrates the equation of motion of a problem with one of the methods, or compares all of
+/// them against its reference solution.
 #[derive(Parser)]
 struct Args {
     #[clap(subcommand)]
-    method: Method,
+    command: Command,
 
-    #[clap(long)]
+    #[clap(long, default_value_t = 5)]
     delta_t_exponent: i32,
+
+    /// Equation of motion to solve
+    #[clap(long, value_enum, default_value_t)]
+    problem: ProblemKind,
+
+    #[clap(flatten)]
+    oscillator: Oscillator,
+
+    /// Removes the damping of the oscillator, to check the conservation of the energy over long
+    /// runs
+    #[clap(long, conflicts_with = "damping")]
+    undamped: bool,
+
+    #[clap(flatten)]
+    pendulum: Pendulum,
+
+    #[clap(flatten)]
+    kepler: Kepler,
+
+    #[clap(flatten)]
+    lennard_jones: LennardJonesDimer,
+
+    #[clap(flatten)]
+    duffing: Duffing,
+
+    /// Duration of the simulation, in the time unit of the problem
+    #[clap(long, default_value_t = 5.0)]
+    final_time: f64,
+
+    /// Duration of the simulation in periods of the problem, 2 pi times its characteristic
+    /// time, instead of the final time
+    #[clap(long, conflicts_with = "final_time")]
+    periods: Option<f64>,
+
+    /// Time between the rows of the output
+    #[clap(long, default_value_t = 0.02)]
+    output_interval: f64,
+
+    /// Also prints the number of evaluations of the acceleration after the MSE, as
+    /// `mse,evaluations`
+    #[clap(long)]
+    evaluations: bool,
+
+    /// Writes the energy and the other invariants of the problem, their drift from the ones of
+    /// the reference solution and the phase space error at every row of the output
+    #[clap(long)]
+    diagnostics: Option<PathBuf>,
+}
+
+/// Runs `method` with steps of `dt` until `tf`, calling back with the state at every step.
+/// Returns the number of evaluations of the acceleration.
+fn simulate<Callback: CallbackFn>(
+    method: &Method,
+    problem: &dyn Problem,
+    dt: f64,
+    tf: f64,
+    callback: Callback,
+) -> usize {
+    if let Some(mut integrator) = method.integrator(problem) {
+        integrate(integrator.as_mut(), problem, dt, tf, callback)
+    } else {
+        reference(problem, dt, tf, callback);
+        0
+    }
+}
+
+/// Euclidean distance between two states.
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Slope of the least squares line of ln(error) against ln(dt), from the largest step down to
+/// the first error that is at most `floor(dt)` or stops decreasing. None with fewer than two
+/// such errors.
+fn fit_order(errors: &[(f64, f64)], floor: impl Fn(f64) -> f64) -> Option<f64> {
+    let mut errors = errors.to_vec();
+    errors.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+    let mut previous = f64::INFINITY;
+    let points: Vec<_> = errors
+        .iter()
+        .take_while(|&&(dt, error)| {
+            let usable = error > floor(dt) && error < previous;
+            previous = error;
+            usable
+        })
+        .map(|(dt, error)| (dt.ln(), error.ln()))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+
+    Some(covariance / variance)
+}
+
+fn convergence(problem: &dyn Problem, delta_t_exponents: &[i32], tf: f64) {
+    assert!(
+        delta_t_exponents.len() >= 2,
+        "The convergence needs at least two steps to fit the orders."
+    );
+    println!("method,dt,mse,max_error,time,evaluations");
+
+    // Positions of the reference solution at every step of every dt, one after the other, so
+    // the runs with a reference integration don't repeat it for every method.
+    let references: Vec<_> = delta_t_exponents
+        .iter()
+        .map(|&exponent| {
+            let mut positions = vec![];
+            reference(problem, 10f64.powi(-exponent), tf, |_, r, _| {
+                positions.extend_from_slice(r)
+            });
+            positions
+        })
+        .collect();
+    let dimension = problem.initial_state().0.len();
+    // Below the round-off accumulated over the steps, or the error of the reference itself, the
+    // errors no longer follow the order of the method.
+    let reference_error = Reference::error(problem);
+    let floor = |dt: f64| (10.0 * f64::EPSILON * tf / dt).max(reference_error);
+
+    let mut orders = vec![];
+    for method in Method::fixed_step() {
+        let mut rms_errors = vec![];
+        let mut max_errors = vec![];
+
+        for (&exponent, expected) in delta_t_exponents.iter().zip(&references) {
+            let dt = 10f64.powi(-exponent);
+            let mut squared_error = 0.0;
+            let mut max_error: f64 = 0.0;
+            let mut steps = 0;
+
+            let start = Instant::now();
+            let evaluations = simulate(&method, problem, dt, tf, |_, r, _| {
+                let expected = &expected[steps * dimension..(steps + 1) * dimension];
+                let error = distance(expected, r);
+                squared_error += error.powi(2);
+                max_error = max_error.max(error);
+                steps += 1;
+            });
+            let time = start.elapsed().as_secs_f64();
+
+            let mse = squared_error / steps as f64;
+            println!(
+                "{},{dt},{mse},{max_error},{time},{evaluations}",
+                method.name()
+            );
+
+            rms_errors.push((dt, mse.sqrt()));
+            max_errors.push((dt, max_error));
+        }
+
+        orders.push((
+            method,
+            fit_order(&rms_errors, floor),
+            fit_order(&max_errors, floor),
+        ));
+    }
+
+    eprintln!("method,rms_error_order,max_error_order");
+    for (method, rms_order, max_order) in &orders {
+        if let (Some(rms_order), Some(max_order)) = (rms_order, max_order) {
+            eprintln!("{},{rms_order},{max_order}", method.name());
+        }
+    }
+    for (method, rms_order, max_order) in &orders {
+        if rms_order.is_none() || max_order.is_none() {
+            eprintln!(
+                "Fewer than two errors of {} are above the round-off and reference floor, use \
+                larger steps to fit its order.",
+                method.name()
+            );
+        }
+    }
 }
 
 fn main() {
     let args = Args::parse();
+    let mut oscillator = args.oscillator;
+    if args.undamped {
+        oscillator.damping = 0.0;
+    }
+    let problem: Box<dyn Problem> = match args.problem {
+        ProblemKind::Oscillator => Box::new(oscillator),
+        ProblemKind::Pendulum => Box::new(args.pendulum),
+        ProblemKind::Kepler => Box::new(args.kepler),
+        ProblemKind::LennardJones => Box::new(args.lennard_jones),
+        ProblemKind::Duffing => Box::new(args.duffing),
+    };
+    let problem = problem.as_ref();
+    let final_time = args.periods.map_or(args.final_time, |periods| {
+        periods * 2.0 * PI * problem.characteristic_time()
+    });
 
-    // Constants
-    const M: f64 = 70.0; // m = 70 kg
-    const K: f64 = 1e4; // k = 10^4 N/m
-    const GAMMA: f64 = 100.0; // gamma = 100 kg/s
-    const A: f64 = 1.0; // A = 1 m
-
-    // Initial Conditions
-    const R: f64 = 1.0; // r(t=0) = 1m
-    const V: f64 = -A * GAMMA / (2.0 * M); // v(t=0) = -a * gamma / (2.0 * m)
+    let method = match args.command {
+        Command::Method(method) => method,
+        Command::Convergence { delta_t_exponents } => {
+            convergence(problem, &delta_t_exponents, final_time);
+            return;
+        }
+    };
 
     let dt = 10f64.powi(-args.delta_t_exponent);
-    let output_every: usize = (0.02 / dt) as usize;
-
-    let calc_force = |r: f64, v: f64| -K * r - GAMMA * v;
-    let calc_initial_integration = |r: f64, v: f64| {
-        let r = r;
-        let r1 = v;
-        let r2 = calc_force(r, r1) / M;
-        let r3 = -K * r1 - GAMMA * r2;
-        let r4 = -K * r2 - GAMMA * r3;
-        let r5 = -K * r3 - GAMMA * r4;
-
-        (r, r1, r2, r3, r4, r5)
-    };
+    let output_every = ((args.output_interval / dt) as usize).max(1);
 
-    let analytic_solution = |t: f64| analytic_solution(A, GAMMA, M, t, K);
+    let mut diagnostics_writer = args.diagnostics.map(|path| {
+        let mut writer = BufWriter::new(File::create(path).unwrap());
+        writer.write_all(b"t").unwrap();
+        for name in problem.invariant_names() {
+            writer
+                .write_fmt(format_args!(",{name},expected_{name},{name}_drift"))
+                .unwrap();
+        }
+        writer.write_all(b",phase_space_error\n").unwrap();
+        writer
+    });
 
+    let mut expected_solution = Reference::new(problem);
     let mut diff = 0.0;
     let mut steps = 0;
-    let print_csv_row = |t: f64, r: f64, v: f64| {
-        diff += (analytic_solution(t) - r).powi(2);
+    let print_csv_row = |t: f64, r: &[f64], v: &[f64]| {
+        let (expected_r, expected_v) = expected_solution.state(t);
+        diff += distance(&expected_r, r).powi(2);
 
         // print every OUTPUT_EVERY steps
         if steps % output_every == 0 {
-            println!("{t:.4},{r},{v}");
+            println!("{t:.4},{}", r.iter().chain(v).join(","));
+
+            if let Some(writer) = &mut diagnostics_writer {
+                writer.write_fmt(format_args!("{t:.4}")).unwrap();
+                let invariants = problem.invariants(r, v);
+                let expected_invariants = problem.invariants(&expected_r, &expected_v);
+                for (value, expected) in invariants.into_iter().zip(expected_invariants) {
+                    // Relative to the expected value, unless it is zero like the momentum of
+                    // the dimer
+                    let scale = if expected == 0.0 { 1.0 } else { expected.abs() };
+                    let drift = (value - expected) / scale;
+                    writer
+                        .write_fmt(format_args!(",{value},{expected},{drift}"))
+                        .unwrap();
+                }
+
+                // Velocity errors multiplied by the characteristic time, so both are lengths
+                let time_scale = problem.characteristic_time();
+                let scaled_v: Vec<_> = v.iter().map(|v| v * time_scale).collect();
+                let expected_scaled_v: Vec<_> = expected_v.iter().map(|v| v * time_scale).collect();
+                let phase_space_error =
+                    distance(&expected_r, r).hypot(distance(&expected_scaled_v, &scaled_v));
+                writer
+                    .write_fmt(format_args!(",{phase_space_error}\n"))
+                    .unwrap();
+            }
         }
 
         steps += 1;
     };
 
-    println!("t,r,v");
-
-    match args.method {
-        Method::Analytic => analytic(analytic_solution, dt, print_csv_row),
-        Method::Gear => gear_predictor_corrector(
-            R,
-            V,
-            calc_force,
-            calc_initial_integration,
-            dt,
-            M,
-            print_csv_row,
-        ),
-        Method::Verlet => verlet(R, V, calc_force, dt, M, print_csv_row),
-        Method::Beeman => beeman(R, V, calc_force, dt, M, print_csv_row),
+    let dimension = problem.initial_state().0.len();
+    if dimension == 1 {
+        println!("t,r,v");
+    } else {
+        println!(
+            "t,{},{}",
+            (0..dimension).map(|i| format!("r{i}")).join(","),
+            (0..dimension).map(|i| format!("v{i}")).join(",")
+        );
     }
 
+    let evaluations = simulate(&method, problem, dt, final_time, print_csv_row);
+
     let mse = diff / steps as f64; // Mean Square Error
-    eprintln!("{}", mse);
+    if args.evaluations {
+        eprintln!("{mse},{evaluations}");
+    } else {
+        eprintln!("{}", mse);
+    }
 }
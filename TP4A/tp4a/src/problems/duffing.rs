@@ -0,0 +1,86 @@
+use super::Problem;
+
+/// Driven Duffing oscillator, `x'' + delta x' + alpha x + beta x^3 = gamma cos(omega t)`,
+/// starting at rest. The default parameters make it chaotic, so the reference solution is only
+/// meaningful for a few tens of periods.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct Duffing {
+    /// Damping coefficient
+    #[clap(long = "duffing-delta", default_value_t = 0.3)]
+    pub delta: f64,
+
+    /// Linear stiffness, negative for a double well
+    #[clap(
+        long = "duffing-alpha",
+        default_value_t = -1.0,
+        allow_negative_numbers = true
+    )]
+    pub alpha: f64,
+
+    /// Cubic stiffness
+    #[clap(
+        long = "duffing-beta",
+        default_value_t = 1.0,
+        allow_negative_numbers = true
+    )]
+    pub beta: f64,
+
+    /// Amplitude of the driving force
+    #[clap(long = "duffing-gamma", default_value_t = 0.5)]
+    pub gamma: f64,
+
+    /// Angular frequency of the driving force
+    #[clap(long = "duffing-omega", default_value_t = 1.2)]
+    pub omega: f64,
+
+    /// Position at t = 0
+    #[clap(
+        id = "duffing-initial-position",
+        long = "duffing-initial-position",
+        default_value_t = 1.0,
+        allow_negative_numbers = true
+    )]
+    pub initial_position: f64,
+}
+
+impl Problem for Duffing {
+    fn initial_state(&self) -> (Vec<f64>, Vec<f64>) {
+        (vec![self.initial_position], vec![0.0])
+    }
+
+    fn acceleration(
+        &self,
+        t: f64,
+        positions: &[f64],
+        velocities: &[f64],
+        accelerations: &mut [f64],
+    ) {
+        let x = positions[0];
+        accelerations[0] = self.gamma * (self.omega * t).cos()
+            - self.delta * velocities[0]
+            - self.alpha * x
+            - self.beta * x.powi(3);
+    }
+
+    fn characteristic_time(&self) -> f64 {
+        1.0 / self.omega
+    }
+
+    fn velocity_dependent(&self) -> bool {
+        self.delta != 0.0
+    }
+
+    fn invariant_names(&self) -> &'static [&'static str] {
+        &["energy"]
+    }
+
+    /// Energy of the undriven oscillator, which the driving force and the damping change.
+    fn invariants(&self, positions: &[f64], velocities: &[f64]) -> Vec<f64> {
+        let x = positions[0];
+        vec![
+            0.5 * velocities[0].powi(2)
+                + 0.5 * self.alpha * x.powi(2)
+                + 0.25 * self.beta * x.powi(4),
+        ]
+    }
+}
@@ -0,0 +1,81 @@
+use super::Problem;
+
+/// Relative motion of two bodies, `r'' = -GM r / |r|^3`, in units where GM and the semi-major
+/// axis are 1, so a period lasts 2 pi. The orbit starts at the periapsis on the x axis and its
+/// coordinates are x and y.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct Kepler {
+    /// Eccentricity of the orbit, from 0 to 1
+    #[clap(long = "kepler-eccentricity", default_value_t = 0.5)]
+    pub eccentricity: f64,
+}
+
+impl Kepler {
+    /// Solves Kepler's equation `E - e sin(E) = M` with Newton's method.
+    fn eccentric_anomaly(&self, mean_anomaly: f64) -> f64 {
+        let e = self.eccentricity;
+        let mut anomaly = mean_anomaly + e * mean_anomaly.sin();
+        for _ in 0..50 {
+            let delta = (anomaly - e * anomaly.sin() - mean_anomaly) / (1.0 - e * anomaly.cos());
+            anomaly -= delta;
+            if delta.abs() <= f64::EPSILON * anomaly.abs().max(1.0) {
+                break;
+            }
+        }
+        anomaly
+    }
+}
+
+impl Problem for Kepler {
+    fn initial_state(&self) -> (Vec<f64>, Vec<f64>) {
+        let e = self.eccentricity;
+        (
+            vec![1.0 - e, 0.0],
+            vec![0.0, ((1.0 + e) / (1.0 - e)).sqrt()],
+        )
+    }
+
+    fn acceleration(
+        &self,
+        _t: f64,
+        positions: &[f64],
+        _velocities: &[f64],
+        accelerations: &mut [f64],
+    ) {
+        let distance_cubed = positions[0].hypot(positions[1]).powi(3);
+        accelerations[0] = -positions[0] / distance_cubed;
+        accelerations[1] = -positions[1] / distance_cubed;
+    }
+
+    fn characteristic_time(&self) -> f64 {
+        1.0
+    }
+
+    fn invariant_names(&self) -> &'static [&'static str] {
+        &["energy", "angular_momentum"]
+    }
+
+    /// Energy and angular momentum per unit of reduced mass.
+    fn invariants(&self, positions: &[f64], velocities: &[f64]) -> Vec<f64> {
+        let [x, y] = [positions[0], positions[1]];
+        let [vx, vy] = [velocities[0], velocities[1]];
+        vec![
+            0.5 * (vx.powi(2) + vy.powi(2)) - 1.0 / x.hypot(y),
+            x * vy - y * vx,
+        ]
+    }
+
+    fn analytic_solution(&self, t: f64) -> Option<(Vec<f64>, Vec<f64>)> {
+        let e = self.eccentricity;
+        // With unit GM and semi-major axis the mean motion is 1, so the mean anomaly is t.
+        let anomaly = self.eccentric_anomaly(t);
+        let (sin, cos) = anomaly.sin_cos();
+        let minor_axis = (1.0 - e.powi(2)).sqrt();
+        let anomaly_rate = 1.0 / (1.0 - e * cos);
+
+        Some((
+            vec![cos - e, minor_axis * sin],
+            vec![-sin * anomaly_rate, minor_axis * cos * anomaly_rate],
+        ))
+    }
+}
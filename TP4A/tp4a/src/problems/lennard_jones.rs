@@ -0,0 +1,71 @@
+use super::Problem;
+
+/// Two atoms on a line bound by the Lennard-Jones potential
+/// `V(d) = 4 epsilon ((sigma / d)^12 - (sigma / d)^6)`, starting at rest. Its coordinates are the
+/// positions of both atoms.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct LennardJonesDimer {
+    /// Depth of the potential well
+    #[clap(long = "lj-epsilon", default_value_t = 1.0)]
+    pub epsilon: f64,
+
+    /// Distance at which the potential is zero
+    #[clap(long = "lj-sigma", default_value_t = 1.0)]
+    pub sigma: f64,
+
+    /// Mass of each atom
+    #[clap(id = "lj-mass", long = "lj-mass", default_value_t = 1.0)]
+    pub mass: f64,
+
+    /// Distance between the atoms at t = 0, in units of sigma
+    #[clap(long = "lj-initial-distance", default_value_t = 1.5)]
+    pub initial_distance: f64,
+}
+
+impl LennardJonesDimer {
+    fn potential(&self, distance: f64) -> f64 {
+        let s6 = (self.sigma / distance).powi(6);
+        4.0 * self.epsilon * (s6.powi(2) - s6)
+    }
+
+    /// Force on the second atom, positive when they repel each other.
+    fn force(&self, distance: f64) -> f64 {
+        let s6 = (self.sigma / distance).powi(6);
+        24.0 * self.epsilon / distance * (2.0 * s6.powi(2) - s6)
+    }
+}
+
+impl Problem for LennardJonesDimer {
+    fn initial_state(&self) -> (Vec<f64>, Vec<f64>) {
+        let half_distance = 0.5 * self.initial_distance * self.sigma;
+        (vec![-half_distance, half_distance], vec![0.0, 0.0])
+    }
+
+    fn acceleration(
+        &self,
+        _t: f64,
+        positions: &[f64],
+        _velocities: &[f64],
+        accelerations: &mut [f64],
+    ) {
+        let acceleration = self.force(positions[1] - positions[0]) / self.mass;
+        accelerations[0] = -acceleration;
+        accelerations[1] = acceleration;
+    }
+
+    fn characteristic_time(&self) -> f64 {
+        (self.mass * self.sigma.powi(2) / self.epsilon).sqrt()
+    }
+
+    fn invariant_names(&self) -> &'static [&'static str] {
+        &["energy", "momentum"]
+    }
+
+    fn invariants(&self, positions: &[f64], velocities: &[f64]) -> Vec<f64> {
+        let kinetic = 0.5 * self.mass * (velocities[0].powi(2) + velocities[1].powi(2));
+        vec![
+            kinetic + self.potential(positions[1] - positions[0]),
+            self.mass * (velocities[0] + velocities[1]),
+        ]
+    }
+}
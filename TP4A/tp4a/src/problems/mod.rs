@@ -0,0 +1,127 @@
+use clap::ValueEnum;
+use integrators::{DormandPrince, Integrator, Tolerances};
+
+pub use duffing::Duffing;
+pub use kepler::Kepler;
+pub use lennard_jones::LennardJonesDimer;
+pub use oscillator::Oscillator;
+pub use pendulum::Pendulum;
+
+mod duffing;
+mod kepler;
+mod lennard_jones;
+mod oscillator;
+mod pendulum;
+
+/// Equation of motion `r'' = a(t, r, r')` of a set of scalar coordinates, with the quantities
+/// used to check the methods against it.
+pub trait Problem {
+    /// Positions and velocities of every coordinate at t = 0.
+    fn initial_state(&self) -> (Vec<f64>, Vec<f64>);
+
+    fn acceleration(
+        &self,
+        t: f64,
+        positions: &[f64],
+        velocities: &[f64],
+        accelerations: &mut [f64],
+    );
+
+    /// Time in which the state changes noticeably, 1 / omega for an oscillation. Velocities are
+    /// multiplied by it to compare them with positions, and the runs measured in periods last
+    /// 2 pi of it per period.
+    fn characteristic_time(&self) -> f64;
+
+    /// Whether the acceleration depends on the velocities, which needs other Gear coefficients.
+    fn velocity_dependent(&self) -> bool {
+        false
+    }
+
+    /// Names of the values returned by `invariants`.
+    fn invariant_names(&self) -> &'static [&'static str];
+
+    /// Energy and the other quantities that are conserved, or change in a known way.
+    fn invariants(&self, positions: &[f64], velocities: &[f64]) -> Vec<f64>;
+
+    /// Positions and velocities at time `t`, for the problems with a closed form solution.
+    fn analytic_solution(&self, _t: f64) -> Option<(Vec<f64>, Vec<f64>)> {
+        None
+    }
+
+    /// Derivatives of every coordinate at t = 0 from the acceleration up to the fifth one, to
+    /// start the Gear methods. Without them the higher derivatives start at zero.
+    fn initial_derivatives(&self) -> Option<Vec<Vec<f64>>> {
+        None
+    }
+}
+
+#[derive(ValueEnum, Default, Debug, Clone, Copy)]
+pub enum ProblemKind {
+    #[default]
+    Oscillator,
+    Pendulum,
+    Kepler,
+    LennardJones,
+    Duffing,
+}
+
+/// Solution the methods are compared against: the analytic one when the problem has it, or a
+/// Dormand-Prince run with tolerances close to the precision of f64 otherwise.
+pub struct Reference<'a> {
+    problem: &'a dyn Problem,
+    integrator: DormandPrince<f64, f64>,
+    time: f64,
+    positions: Vec<f64>,
+    velocities: Vec<f64>,
+}
+
+impl<'a> Reference<'a> {
+    const TOLERANCE: f64 = 1e-12;
+
+    pub fn new(problem: &'a dyn Problem) -> Self {
+        let (positions, velocities) = problem.initial_state();
+        Self {
+            problem,
+            integrator: DormandPrince::new(Tolerances {
+                absolute: Self::TOLERANCE,
+                relative: Self::TOLERANCE,
+            }),
+            time: 0.0,
+            positions,
+            velocities,
+        }
+    }
+
+    /// Bound of the error of the reference solution of `problem`, zero for the analytic ones. The
+    /// global error of the Dormand-Prince run reaches about ten times its tolerance, so the bound
+    /// is a hundred times it to keep the errors within a factor ten of it out of the fits.
+    pub fn error(problem: &dyn Problem) -> f64 {
+        if problem.analytic_solution(0.0).is_some() {
+            0.0
+        } else {
+            100.0 * Self::TOLERANCE
+        }
+    }
+
+    /// Positions and velocities at time `t`, which must not be before the one of the previous
+    /// call.
+    pub fn state(&mut self, t: f64) -> (Vec<f64>, Vec<f64>) {
+        if let Some(state) = self.problem.analytic_solution(t) {
+            return state;
+        }
+
+        if t > self.time {
+            let problem = self.problem;
+            self.integrator.step(
+                self.time,
+                t - self.time,
+                &mut self.positions,
+                &mut self.velocities,
+                &mut |t, r, v, a| problem.acceleration(t, r, v, a),
+            );
+            self.time = t;
+        }
+
+        (self.positions.clone(), self.velocities.clone())
+    }
+}
@@ -0,0 +1,120 @@
+use super::Problem;
+
+/// Damped harmonic oscillator, `m r'' = -k r - gamma r'`.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct Oscillator {
+    /// Mass in kg
+    #[clap(long, default_value_t = 70.0)]
+    pub mass: f64,
+
+    /// Spring constant in N/m
+    #[clap(long, default_value_t = 1e4)]
+    pub spring_constant: f64,
+
+    /// Damping coefficient in kg/s
+    #[clap(long, default_value_t = 100.0)]
+    pub damping: f64,
+
+    /// Position at t = 0 in m
+    #[clap(long, default_value_t = 1.0, allow_negative_numbers = true)]
+    pub initial_position: f64,
+
+    /// Velocity at t = 0 in m/s. Defaults to -r0 gamma / (2 m), which makes the underdamped
+    /// solution a damped cosine.
+    #[clap(long, allow_negative_numbers = true)]
+    pub initial_velocity: Option<f64>,
+}
+
+impl Oscillator {
+    pub fn force(&self, r: f64, v: f64) -> f64 {
+        -self.spring_constant * r - self.damping * v
+    }
+
+    pub fn initial_velocity(&self) -> f64 {
+        self.initial_velocity
+            .unwrap_or(-self.initial_position * self.damping / (2.0 * self.mass))
+    }
+
+    /// Kinetic plus elastic potential energy.
+    pub fn energy(&self, r: f64, v: f64) -> f64 {
+        0.5 * self.mass * v.powi(2) + 0.5 * self.spring_constant * r.powi(2)
+    }
+}
+
+impl Problem for Oscillator {
+    fn initial_state(&self) -> (Vec<f64>, Vec<f64>) {
+        (vec![self.initial_position], vec![self.initial_velocity()])
+    }
+
+    fn acceleration(
+        &self,
+        _t: f64,
+        positions: &[f64],
+        velocities: &[f64],
+        accelerations: &mut [f64],
+    ) {
+        accelerations[0] = self.force(positions[0], velocities[0]) / self.mass;
+    }
+
+    fn characteristic_time(&self) -> f64 {
+        (self.mass / self.spring_constant).sqrt()
+    }
+
+    fn velocity_dependent(&self) -> bool {
+        self.damping != 0.0
+    }
+
+    fn invariant_names(&self) -> &'static [&'static str] {
+        &["energy"]
+    }
+
+    fn invariants(&self, positions: &[f64], velocities: &[f64]) -> Vec<f64> {
+        vec![self.energy(positions[0], velocities[0])]
+    }
+
+    fn analytic_solution(&self, t: f64) -> Option<(Vec<f64>, Vec<f64>)> {
+        let r0 = self.initial_position;
+        let v0 = self.initial_velocity();
+        let beta = self.damping / (2.0 * self.mass);
+        let natural_frequency_squared = self.spring_constant / self.mass;
+        let discriminant = natural_frequency_squared - beta.powi(2);
+        let decay = (-beta * t).exp();
+
+        let (r, v) = if discriminant.abs() <= f64::EPSILON * natural_frequency_squared {
+            // Critically damped
+            let c = v0 + beta * r0;
+            let r = decay * (r0 + c * t);
+            (r, decay * c - beta * r)
+        } else if discriminant > 0.0 {
+            // Underdamped
+            let omega = discriminant.sqrt();
+            let c = (v0 + beta * r0) / omega;
+            let (sin, cos) = (omega * t).sin_cos();
+            (
+                decay * (r0 * cos + c * sin),
+                decay * (v0 * cos - (r0 * omega + beta * c) * sin),
+            )
+        } else {
+            // Overdamped, the sum of two decaying exponentials
+            let s = (-discriminant).sqrt();
+            let (lambda_1, lambda_2) = (-beta + s, -beta - s);
+            let c1 = (v0 - lambda_2 * r0) / (lambda_1 - lambda_2);
+            let c2 = r0 - c1;
+            let (e1, e2) = ((lambda_1 * t).exp(), (lambda_2 * t).exp());
+            (c1 * e1 + c2 * e2, c1 * lambda_1 * e1 + c2 * lambda_2 * e2)
+        };
+
+        Some((vec![r], vec![v]))
+    }
+
+    /// Every derivative of the equation of motion gives the next one from the previous two.
+    fn initial_derivatives(&self) -> Option<Vec<Vec<f64>>> {
+        let mut rs = [0.0; 6];
+        rs[0] = self.initial_position;
+        rs[1] = self.initial_velocity();
+        for n in 2..rs.len() {
+            rs[n] = self.force(rs[n - 2], rs[n - 1]) / self.mass;
+        }
+        Some(vec![rs[2..].to_vec()])
+    }
+}
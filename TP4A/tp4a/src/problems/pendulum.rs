@@ -0,0 +1,53 @@
+use super::Problem;
+
+/// Simple pendulum without the small angle approximation, `theta'' = -g / L sin(theta)`. Its
+/// coordinate is the angle from the vertical.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct Pendulum {
+    /// Length of the pendulum in m
+    #[clap(long = "pendulum-length", default_value_t = 1.0)]
+    pub length: f64,
+
+    /// Gravitational acceleration in m/s^2
+    #[clap(long, default_value_t = 9.81)]
+    pub gravity: f64,
+
+    /// Angle of the pendulum at t = 0 in rad, starting at rest
+    #[clap(
+        long = "pendulum-initial-angle",
+        default_value_t = 2.0,
+        allow_negative_numbers = true
+    )]
+    pub initial_angle: f64,
+}
+
+impl Problem for Pendulum {
+    fn initial_state(&self) -> (Vec<f64>, Vec<f64>) {
+        (vec![self.initial_angle], vec![0.0])
+    }
+
+    fn acceleration(
+        &self,
+        _t: f64,
+        positions: &[f64],
+        _velocities: &[f64],
+        accelerations: &mut [f64],
+    ) {
+        accelerations[0] = -self.gravity / self.length * positions[0].sin();
+    }
+
+    fn characteristic_time(&self) -> f64 {
+        (self.length / self.gravity).sqrt()
+    }
+
+    fn invariant_names(&self) -> &'static [&'static str] {
+        &["energy"]
+    }
+
+    /// Energy per unit mass.
+    fn invariants(&self, positions: &[f64], velocities: &[f64]) -> Vec<f64> {
+        let kinetic = 0.5 * (self.length * velocities[0]).powi(2);
+        let potential = self.gravity * self.length * (1.0 - positions[0].cos());
+        vec![kinetic + potential]
+    }
+}
@@ -37,10 +37,11 @@ fn main() {
     let mut analysis_file = File::create(args.analysis).unwrap();
 
     for frame in output_parser(BufReader::new(output_file).lines()) {
-        let Frame { time, balls } = frame;
+        let Frame { time, mut balls } = frame;
+        system_info.restore_properties(&mut balls);
         let energy: Float = balls
             .iter()
-            .map(|p| 0.5 * system_info.ball_mass * p.velocity.magnitude().powi(2))
+            .map(|p| 0.5 * p.mass * p.velocity.magnitude().powi(2))
             .sum();
         let ball_count = balls.len();
 
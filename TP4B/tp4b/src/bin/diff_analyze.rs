@@ -5,7 +5,7 @@ use std::{
 };
 
 use clap::Parser as _parser;
-use pool::{models::Frame, parser::output_parser};
+use pool::{models::Frame, parser::output_parser, Float};
 
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -49,7 +49,7 @@ fn main() {
 
         balls1.sort_by_key(|ball| ball.id);
         balls2.sort_by_key(|ball| ball.id);
-        let phi: f64 = balls1
+        let phi: Float = balls1
             .iter()
             .zip(balls2.iter())
             .map(|(ball1, ball2)| (ball2.position - ball1.position).magnitude())
@@ -1,14 +1,14 @@
 #![feature(let_chains)]
-#![feature(btree_drain_filter)]
 
 use chumsky::Parser;
 use cim::{
     /*cim_finder::CimNeighborFinder, */ neighbor_finder::NeighborFinder, particles::ID,
     simple_finder::SimpleNeighborFinder,
 };
-use gear_predictor_corrector::{GearCorrector, GearPredictor};
+use integrators::{Gear, GearCoefficients, GearInitialization, Integrator};
+use itertools::Itertools;
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::HashMap,
     fs::{self, File},
     io::{stdout, Write},
 };
@@ -17,7 +17,7 @@ use nalgebra::Vector2;
 use pool::{
     models::{Ball, InputData as SimpleInputData, IterableFrame},
     parser::input_parser,
-    Float, HOLE_POSITIONS,
+    Float,
 };
 
 use clap::{Args, Parser as _parser, Subcommand};
@@ -55,10 +55,28 @@ struct Arguments {
     with_holes: bool,
 
     #[arg(short, long)]
-    max_duration: Option<f64>,
+    max_duration: Option<Float>,
 
     #[arg(long)]
     min_ball_amount: Option<usize>,
+
+    /// Highest derivative of the position kept by the Gear predictor-corrector, from 2 to 5
+    #[arg(
+        long,
+        default_value_t = 5,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..=5)
+    )]
+    gear_order: usize,
+
+    /// Corrector coefficients of the Gear predictor-corrector, `position` or `velocity`. The
+    /// contact forces only depend on the positions.
+    #[arg(long, default_value_t = GearCoefficients::Position)]
+    gear_coefficients: GearCoefficients,
+
+    /// Estimation of the derivatives above the acceleration at the start, `zero`,
+    /// `finite-differences` or `bootstrap`. They are only zero for the balls that start apart.
+    #[arg(long, default_value_t = GearInitialization::Bootstrap)]
+    gear_initialization: GearInitialization,
 }
 
 struct InputData {
@@ -66,6 +84,9 @@ struct InputData {
     output_condition: OutputCondition,
     delta_time_n: u16,
     with_holes: bool,
+    gear_order: usize,
+    gear_coefficients: GearCoefficients,
+    gear_initialization: GearInitialization,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -80,7 +101,7 @@ const K: Float = 10e4 * 1000.0;
 
 fn did_ball_go_outside(ball: &Ball, config: &InputData) -> Vec<Wall> {
     let mut collisions = Vec::with_capacity(2);
-    let radius = config.simple_input_data.ball_radius;
+    let radius = ball.radius;
 
     if ball.position.x - radius < 0.0 {
         collisions.push(Wall::Left)
@@ -102,53 +123,25 @@ fn calculate_force(b: &Ball, other: &Ball, radius_sum: Float) -> Vector2<Float>
     K * ((b.position - other.position).magnitude() - radius_sum) * r_hat
 }
 
-trait PredictorFromBall: Sized {
-    fn from_ball(
-        ball: &Ball,
-        r2: Vector2<f64>,
-        r3: Vector2<f64>,
-        r4: Vector2<f64>,
-        r5: Vector2<f64>,
-    ) -> Self;
-}
-
-impl PredictorFromBall for GearPredictor<Vector2<f64>> {
-    fn from_ball(
-        ball: &Ball,
-        r2: Vector2<f64>,
-        r3: Vector2<f64>,
-        r4: Vector2<f64>,
-        r5: Vector2<f64>,
-    ) -> Self {
-        Self {
-            rs: [ball.position, ball.velocity, r2, r3, r4, r5],
-        }
-    }
+/// Indices of the balls in the state by their ID.
+fn ball_indices(balls: &[Ball]) -> HashMap<ID, usize> {
+    balls.iter().enumerate().map(|(i, b)| (b.id, i)).collect()
 }
 
-fn run<W: Write, F: FnMut(&BTreeMap<ID, (Ball, [Vector2<f64>; 4])>, Float) -> bool>(
+fn run<W: Write, F: FnMut(&[Ball], Float) -> bool>(
     config: InputData,
     mut output_writer: W,
     mut stop_condition: F,
 ) {
     let mut time = 0.0;
-    let mut state: BTreeMap<_, _> = config
-        .simple_input_data
-        .balls
-        .iter()
-        .copied()
-        .map(|p| (p.id, (p, [Vector2::zeros(); 4])))
-        .collect();
+    let mut balls = config.simple_input_data.balls.clone();
+    balls.sort_by_key(|b| b.id);
+    let mut positions = balls.iter().map(|b| b.position).collect_vec();
+    let mut velocities = balls.iter().map(|b| b.velocity).collect_vec();
+    let mut indices = ball_indices(&balls);
 
     let holes = if config.with_holes {
-        HOLE_POSITIONS
-            .map(|v| {
-                v.component_mul(&Vector2::new(
-                    config.simple_input_data.table_width,
-                    config.simple_input_data.table_height,
-                ))
-            })
-            .to_vec()
+        config.simple_input_data.pockets.clone()
     } else {
         vec![]
     };
@@ -156,13 +149,13 @@ fn run<W: Write, F: FnMut(&BTreeMap<ID, (Ball, [Vector2<f64>; 4])>, Float) -> bo
     if match &config.output_condition {
         OutputCondition::Every(..) => true,
         OutputCondition::WhenBallCountHits { counts } => {
-            counts.iter().any(|&count| state.len() <= count)
+            counts.iter().any(|&count| balls.len() <= count)
         }
     } {
         // Write to output
         IterableFrame {
             time,
-            balls: state.values().map(|(b, _)| b),
+            balls: balls.iter(),
         }
         .write_to(&mut output_writer)
         .unwrap();
@@ -171,141 +164,157 @@ fn run<W: Write, F: FnMut(&BTreeMap<ID, (Ball, [Vector2<f64>; 4])>, Float) -> bo
     let delta_time = (10.0 as Float).powi(-(config.delta_time_n as i32));
     let mut iteration = 0;
 
-    let mut predictions = BTreeMap::new();
+    let (coefficients, initialization) = (config.gear_coefficients, config.gear_initialization);
+    let mut integrator: Box<dyn Integrator<Float, Vector2<Float>>> = match config.gear_order {
+        2 => Box::new(Gear::<_, 3>::new(coefficients, initialization)),
+        3 => Box::new(Gear::<_, 4>::new(coefficients, initialization)),
+        4 => Box::new(Gear::<_, 5>::new(coefficients, initialization)),
+        5 => Box::new(Gear::<_, 6>::new(coefficients, initialization)),
+        _ => unreachable!(),
+    };
     let mut predicted_balls = Vec::new();
-    let mut forces = HashMap::new();
-
-    while !stop_condition(&state, time) {
-        let radius_sum = config.simple_input_data.ball_radius * 2.0;
-
-        predictions.clear();
-        predictions.extend(state.iter().map(|(&id, (b, [r2, r3, r4, r5]))| {
-            (
-                id,
-                GearPredictor::from_ball(b, *r2, *r3, *r4, *r5).predict(delta_time),
-            )
-        }));
-
-        predicted_balls.clear();
-        predicted_balls.extend(predictions.iter().map(|(&id, pred)| Ball {
-            id,
-            radius: state[&id].0.radius,
-            position: pred.predictions[0],
-            velocity: pred.predictions[1],
-        }));
-
-        let neighbors = SimpleNeighborFinder::find_neighbors(
-            &predicted_balls,
-            cim::simple_finder::SystemInfo {
-                cyclic: false,
-                interaction_radius: 0.0,
-                space_width: config.simple_input_data.table_width,
-                space_height: config.simple_input_data.table_height,
-            },
-        );
-
-        /*
-         * It's slower for normal ball count but faster for more balls
-        let neighbors = CimNeighborFinder::find_neighbors(
-            &state.values().cloned().collect_vec(),
-            cim::cim_finder::SystemInfo {
-                cyclic: false,
-                interaction_radius: 0.0,
-                space_width: config.simple_input_data.table_width,
-                space_height: config.simple_input_data.table_height,
-                columns: (config.simple_input_data.table_width / radius_sum).floor() as usize,
-                rows: (config.simple_input_data.table_height / radius_sum).floor() as usize,
-            },
-        );
-        */
-
-        forces.clear();
-        forces.extend(state.iter().map(|(&k, _)| (k, Vector2::zeros())));
-
-        let get_predicted_ball = |corrector: &GearCorrector<_>, original_ball: &Ball| {
-            let &Ball { id, radius, .. } = original_ball;
-            let &GearCorrector {
-                predictions: [position, velocity, ..],
-            } = corrector;
-            Ball {
-                id,
-                radius,
-                position,
-                velocity,
-            }
-        };
-
-        for (id, ball) in predictions
-            .iter()
-            .map(|(&id, corrector)| (id, get_predicted_ball(corrector, &state[&id].0)))
-        {
-            let neighs = neighbors.get_neighbors(id);
-
-            for other in neighs
-                .filter(|other_id| id > **other_id)
-                .map(|id| get_predicted_ball(&predictions[id], &state[id].0))
-            {
-                let force = calculate_force(&ball, &other, radius_sum);
-                *forces.get_mut(&ball.id).unwrap() += force;
-                *forces.get_mut(&other.id).unwrap() -= force;
-            }
 
-            let walls = did_ball_go_outside(&ball, &config);
-            for wall in walls {
-                match wall {
-                    Wall::Left => {
-                        let depth = -(ball.position.x - ball.radius);
-                        forces.get_mut(&ball.id).unwrap().x += K * depth;
-                    }
-                    Wall::Right => {
-                        let depth =
-                            ball.position.x - config.simple_input_data.table_width + ball.radius;
-                        forces.get_mut(&ball.id).unwrap().x -= K * depth;
+    while !stop_condition(&balls, time) {
+        let mut acceleration =
+            |_t: Float,
+             positions: &[Vector2<Float>],
+             velocities: &[Vector2<Float>],
+             accelerations: &mut [Vector2<Float>]| {
+                predicted_balls.clear();
+                predicted_balls.extend(balls.iter().zip(positions).zip(velocities).map(
+                    |((ball, &position), &velocity)| Ball {
+                        position,
+                        velocity,
+                        ..*ball
+                    },
+                ));
+
+                // The neighbor finder always works with f64
+                #[allow(clippy::unnecessary_cast)]
+                let neighbors = SimpleNeighborFinder::find_neighbors(
+                    &predicted_balls,
+                    cim::simple_finder::SystemInfo {
+                        cyclic: false,
+                        interaction_radius: 0.0,
+                        space_width: config.simple_input_data.table_width as f64,
+                        space_height: config.simple_input_data.table_height as f64,
+                    },
+                );
+
+                /*
+                 * It's slower for normal ball count but faster for more balls
+                let cell_size = 2.0 * config.simple_input_data.max_ball_radius();
+                let neighbors = CimNeighborFinder::find_neighbors(
+                    &predicted_balls,
+                    cim::cim_finder::SystemInfo {
+                        cyclic: false,
+                        interaction_radius: 0.0,
+                        space_width: config.simple_input_data.table_width,
+                        space_height: config.simple_input_data.table_height,
+                        columns: (config.simple_input_data.table_width / cell_size).floor() as usize,
+                        rows: (config.simple_input_data.table_height / cell_size).floor() as usize,
+                    },
+                );
+                */
+
+                // Accumulate the forces and divide them by the masses at the end.
+                accelerations.fill(Vector2::zeros());
+
+                for (i, ball) in predicted_balls.iter().enumerate() {
+                    for other in neighbors
+                        .get_neighbors(ball.id)
+                        .filter(|other_id| ball.id > **other_id)
+                        .map(|id| &predicted_balls[indices[id]])
+                    {
+                        let force = calculate_force(ball, other, ball.radius + other.radius);
+                        accelerations[i] += force;
+                        accelerations[indices[&other.id]] -= force;
                     }
-                    Wall::Bottom => {
-                        let depth = -(ball.position.y - ball.radius);
-                        forces.get_mut(&ball.id).unwrap().y += K * depth;
+
+                    let walls = did_ball_go_outside(ball, &config);
+                    for wall in walls {
+                        match wall {
+                            Wall::Left => {
+                                let depth = -(ball.position.x - ball.radius);
+                                accelerations[i].x += K * depth;
+                            }
+                            Wall::Right => {
+                                let depth = ball.position.x - config.simple_input_data.table_width
+                                    + ball.radius;
+                                accelerations[i].x -= K * depth;
+                            }
+                            Wall::Bottom => {
+                                let depth = -(ball.position.y - ball.radius);
+                                accelerations[i].y += K * depth;
+                            }
+                            Wall::Top => {
+                                let depth = ball.position.y - config.simple_input_data.table_height
+                                    + ball.radius;
+                                accelerations[i].y -= K * depth;
+                            }
+                        }
                     }
-                    Wall::Top => {
-                        let depth =
-                            ball.position.y - config.simple_input_data.table_height + ball.radius;
-                        forces.get_mut(&ball.id).unwrap().y -= K * depth;
+
+                    for cushion in &config.simple_input_data.cushions {
+                        let contact = cushion.closest_point(&ball.position);
+                        let distance = (ball.position - contact).magnitude();
+                        // A centre right on the cushion has no direction to be pushed along.
+                        if distance > 0.0 && distance < ball.radius {
+                            accelerations[i] +=
+                                K * (ball.radius - distance) * (ball.position - contact) / distance;
+                        }
                     }
                 }
-            }
-        }
 
-        for (id, (ball, higher_order)) in state.iter_mut() {
-            let force = forces.get(id).cloned().unwrap_or_else(Vector2::zeros);
-            let acceleration = force / config.simple_input_data.ball_mass;
-            let [p, v, r2, r3, r4, r5] = predictions[id].correct(acceleration, delta_time);
-            ball.position = p;
-            ball.velocity = v;
-            *higher_order = [r2, r3, r4, r5];
+                for (acceleration, ball) in accelerations.iter_mut().zip(&predicted_balls) {
+                    *acceleration /= ball.mass;
+                }
+            };
+
+        integrator.step(
+            time,
+            delta_time,
+            &mut positions,
+            &mut velocities,
+            &mut acceleration,
+        );
+
+        for ((ball, &position), &velocity) in balls.iter_mut().zip(&positions).zip(&velocities) {
+            ball.position = position;
+            ball.velocity = velocity;
         }
 
-        let removed_balls = state
-            .drain_filter(|_, (ball, _)| {
-                holes.iter().any(|hole| {
-                    (hole - ball.position).magnitude_squared()
-                        <= (config.simple_input_data.hole_radius + ball.radius).powi(2)
-                })
-            })
-            .count();
+        let mut removed_balls = 0;
+        for i in (0..balls.len()).rev() {
+            let ball = &balls[i];
+            if holes.iter().any(|hole| {
+                (hole.position - ball.position).magnitude_squared()
+                    <= (hole.radius + ball.radius).powi(2)
+            }) {
+                balls.remove(i);
+                positions.remove(i);
+                velocities.remove(i);
+                integrator.remove(i);
+                removed_balls += 1;
+            }
+        }
+        if removed_balls > 0 {
+            indices = ball_indices(&balls);
+        }
 
         iteration += 1;
-        time = iteration as f64 * delta_time;
+        time = iteration as Float * delta_time;
 
         if match &config.output_condition {
             OutputCondition::Every(EveryArgs { steps, .. }) => iteration % steps == 0,
             OutputCondition::WhenBallCountHits { counts } => counts
                 .iter()
-                .any(|&count| state.len() <= count && state.len() + removed_balls > count),
+                .any(|&count| balls.len() <= count && balls.len() + removed_balls > count),
         } {
             // Write to output
             IterableFrame {
                 time,
-                balls: state.values().map(|(b, _)| b),
+                balls: balls.iter(),
             }
             .write_to(&mut output_writer)
             .unwrap();
@@ -319,7 +328,7 @@ fn run<W: Write, F: FnMut(&BTreeMap<ID, (Ball, [Vector2<f64>; 4])>, Float) -> bo
     } {
         IterableFrame {
             time,
-            balls: state.values().map(|(b, _)| b),
+            balls: balls.iter(),
         }
         .write_to(&mut output_writer)
         .unwrap();
@@ -338,6 +347,9 @@ fn main() {
         delta_time_n: args.delta_time_n,
         with_holes: args.with_holes,
         output_condition: args.output_condition,
+        gear_order: args.gear_order,
+        gear_coefficients: args.gear_coefficients,
+        gear_initialization: args.gear_initialization,
     };
 
     let writer = if let Some(output) = args.output {
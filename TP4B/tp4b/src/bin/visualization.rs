@@ -3,13 +3,11 @@
 use capturable_visualization::VisualizationBuilder;
 use chumsky::Parser;
 use clap::Parser as _parser;
-use nalgebra::Vector2;
 use nannou::prelude::*;
 use pool::{
     draw::draw as draw_pool,
-    models::{Frame, InputData},
+    models::{Frame, InputData, Pocket},
     parser::{input_parser, output_parser},
-    Float, HOLE_POSITIONS,
 };
 use std::{
     fs::{read_to_string, File},
@@ -52,7 +50,7 @@ struct Model {
     system_info: InputData,
     frame_iter: Box<dyn Iterator<Item = Frame>>,
     frame: Frame,
-    holes: Vec<Vector2<Float>>,
+    holes: Vec<Pocket>,
 }
 
 fn model(_app: &App, args: Args) -> Model {
@@ -68,12 +66,7 @@ fn model(_app: &App, args: Args) -> Model {
     let holes = if args.no_holes {
         Vec::with_capacity(0)
     } else {
-        Vec::from(HOLE_POSITIONS.map(|v| {
-            v.component_mul(&Vector2::new(
-                system_info.table_width,
-                system_info.table_height,
-            ))
-        }))
+        system_info.pockets.clone()
     };
 
     Model {
@@ -88,7 +81,8 @@ fn model(_app: &App, args: Args) -> Model {
 }
 
 fn update(_app: &App, model: &mut Model, _update: Update) {
-    if let Some(frame) = model.frame_iter.next() {
+    if let Some(mut frame) = model.frame_iter.next() {
+        model.system_info.restore_properties(&mut frame.balls);
         model.frame = frame;
     }
 }
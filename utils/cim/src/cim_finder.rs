@@ -19,20 +19,29 @@ pub struct SystemInfo {
     pub rows: usize,
 }
 
+impl SystemInfo {
+    pub fn cell_size(&self) -> Vector2<f64> {
+        Vector2::new(
+            self.space_width / self.columns as f64,
+            self.space_height / self.rows as f64,
+        )
+    }
+
+    /// Index (column, row) of the cell that contains `position`.
+    pub fn cell_index(&self, position: Vector2<f64>) -> Vector2<usize> {
+        position
+            .component_div(&self.cell_size())
+            .apply_into(|v| *v = v.floor())
+            .try_cast()
+            .unwrap()
+    }
+}
+
 impl<P: CircularParticle> NeighborFinder<P, SystemInfo> for CimNeighborFinder {
     fn find_neighbors(particles: &[P], system: SystemInfo) -> NeighborMap<ID> {
         let mut cells: BTreeMap<(_, _), Vec<P>> = BTreeMap::new();
 
-        let cell_width = system.space_width / system.columns as f64;
-        let cell_height = system.space_height / system.rows as f64;
-        let get_cell_index = |particle: &P| -> Vector2<usize> {
-            particle
-                .get_position()
-                .component_div(&Vector2::new(cell_width, cell_height))
-                .apply_into(|v| *v = v.floor())
-                .try_cast()
-                .unwrap()
-        };
+        let get_cell_index = |particle: &P| system.cell_index(particle.get_position());
         let get_cells_to_check = |cell_index: Vector2<usize>| {
             [
                 Vector2::new(0i32, 0),
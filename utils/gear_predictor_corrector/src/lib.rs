@@ -1,5 +1,10 @@
-use num_traits::Float;
-use std::{ops::{Add, Div, Mul, Sub}, fmt::Debug};
+use nalgebra::{convert, RealField};
+use num_traits::Zero;
+use std::{
+    fmt::Display,
+    ops::{Mul, Sub},
+    str::FromStr,
+};
 
 const fn fac(n: u64) -> u64 {
     match n {
@@ -9,65 +14,395 @@ const fn fac(n: u64) -> u64 {
     }
 }
 
-fn fac_f<F: Float>(n: u64) -> F {
-    F::from(fac(n)).unwrap()
+fn fac_f<S: RealField + Copy>(n: u64) -> S {
+    convert(fac(n) as f64)
 }
 
-pub struct GearPredictor<T> {
-    pub rs: [T; 6],
+/// Position or one of its derivatives for one particle, like a scalar or an `SVector<S, D>`.
+pub trait Derivative<S>: Copy + Zero + Sub<Self, Output = Self> + Mul<S, Output = Self> {}
+
+impl<S, T> Derivative<S> for T where T: Copy + Zero + Sub<T, Output = T> + Mul<S, Output = T> {}
+
+/// Fails to compile the methods used with a number of terms without coefficients.
+struct Terms<const N: usize>;
+
+impl<const N: usize> Terms<N> {
+    const SUPPORTED: () = assert!(
+        3 <= N && N <= 6,
+        "Gear is only implemented for 3 to 6 terms, orders 2 to 5"
+    );
+}
+
+/// Table of corrector coefficients for second order equations. The ones for forces that depend
+/// on the velocities differ from the others in the first coefficient of orders 4 and 5, and
+/// using the wrong one lowers the accuracy without any other sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coefficients {
+    /// For forces that only depend on the positions.
+    Position,
+    /// For forces that also depend on the velocities, like damping.
+    Velocity,
+}
+
+impl Coefficients {
+    /// Coefficients of the corrections of the `N` terms, from the position up.
+    pub fn alphas<const N: usize>(self) -> [f64; N] {
+        #[allow(clippy::let_unit_value)]
+        let () = Terms::<N>::SUPPORTED;
+
+        let alphas: &[f64] = match (N, self) {
+            (3, _) => &[0.0, 1.0, 1.0],
+            (4, _) => &[1.0 / 6.0, 5.0 / 6.0, 1.0, 1.0 / 3.0],
+            (5, Self::Position) => &[19.0 / 120.0, 3.0 / 4.0, 1.0, 1.0 / 2.0, 1.0 / 12.0],
+            (5, Self::Velocity) => &[19.0 / 90.0, 3.0 / 4.0, 1.0, 1.0 / 2.0, 1.0 / 12.0],
+            (6, Self::Position) => &[
+                3.0 / 20.0,
+                251.0 / 360.0,
+                1.0,
+                11.0 / 18.0,
+                1.0 / 6.0,
+                1.0 / 60.0,
+            ],
+            (6, Self::Velocity) => &[
+                3.0 / 16.0,
+                251.0 / 360.0,
+                1.0,
+                11.0 / 18.0,
+                1.0 / 6.0,
+                1.0 / 60.0,
+            ],
+            _ => unreachable!(),
+        };
+        alphas.try_into().unwrap()
+    }
+}
+
+impl FromStr for Coefficients {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "position" => Ok(Coefficients::Position),
+            "velocity" => Ok(Coefficients::Velocity),
+            _ => Err(format!(
+                "unknown coefficients `{s}`, expected `position` or `velocity`"
+            )),
+        }
+    }
+}
+
+impl Display for Coefficients {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Coefficients::Position => "position",
+            Coefficients::Velocity => "velocity",
+        })
+    }
 }
 
-pub struct GearCorrector<T> {
-    pub predictions: [T; 6],
+/// Position and its derivatives up to the order `N - 1`.
+#[derive(Debug, Clone, Copy)]
+pub struct GearPredictor<T, const N: usize = 6> {
+    pub rs: [T; N],
 }
 
-impl<T: Copy + Sub<T, Output = T> + Add<T, Output = T>> GearPredictor<T> {
-    pub fn predict<F: Float + Debug>(self, dt: F) -> GearCorrector<T>
+/// Predicted position and derivatives, waiting for the acceleration at the predicted state.
+#[derive(Debug, Clone, Copy)]
+pub struct GearCorrector<T, const N: usize = 6> {
+    pub predictions: [T; N],
+}
+
+/// Factors of the Taylor series of the predictions, `dt^n / n!`.
+fn taylor_factors<S: RealField + Copy, const N: usize>(dt: S) -> [S; N] {
+    #[allow(clippy::let_unit_value)]
+    let () = Terms::<N>::SUPPORTED;
+
+    std::array::from_fn(|n| dt.powi(n as i32) / fac_f(n as u64))
+}
+
+fn predict_with<S: RealField + Copy, T: Derivative<S>, const N: usize>(
+    rs: &[T; N],
+    taylor: &[S; N],
+) -> [T; N] {
+    std::array::from_fn(|k| (k + 1..N).fold(rs[k], |sum, j| sum + rs[j] * taylor[j - k]))
+}
+
+/// Factors of the corrections of every term for the delta of r2, `alpha_k k! / dt^k`.
+fn correction_factors<S: RealField + Copy, const N: usize>(
+    dt: S,
+    coefficients: Coefficients,
+) -> [S; N] {
+    let alphas = coefficients.alphas::<N>();
+    std::array::from_fn(|k| convert::<_, S>(alphas[k]) * fac_f(k as u64) / dt.powi(k as i32))
+}
+
+fn correct_with<S: RealField + Copy, T: Derivative<S>, const N: usize>(
+    predictions: &[T; N],
+    r2: T,
+    half_dt_squared: S,
+    factors: &[S; N],
+) -> [T; N] {
+    // Calculate the delta
+    let dr2 = (r2 - predictions[2]) * half_dt_squared; // delta r2
+
+    // Calculate the corrections
+    std::array::from_fn(|k| predictions[k] + dr2 * factors[k])
+}
+
+impl<T, const N: usize> GearPredictor<T, N> {
+    /// Moves every derivative `dt` forward with its Taylor series.
+    pub fn predict<S: RealField + Copy>(self, dt: S) -> GearCorrector<T, N>
     where
-        T: Div<F, Output = T> + Mul<F, Output = T> + Debug,
+        T: Derivative<S>,
     {
-        let [r, r1, r2, r3, r4, r5] = self.rs;
-
         GearCorrector {
-            predictions: [
-                r + r1 * dt
-                    + r2 * dt.powi(2) / fac_f(2)
-                    + r3 * dt.powi(3) / fac_f(3)
-                    + r4 * dt.powi(4) / fac_f(4)
-                    + r5 * dt.powi(5) / fac_f(5),
-                r1 + r2 * dt
-                    + r3 * dt.powi(2) / fac_f(2)
-                    + r4 * dt.powi(3) / fac_f(3)
-                    + r5 * dt.powi(4) / fac_f(4),
-                r2 + r3 * dt + r4 * dt.powi(2) / fac_f(2) + r5 * dt.powi(3) / fac_f(3),
-                r3 + r4 * dt + r5 * dt.powi(2) / fac_f(2),
-                r4 + r5 * dt,
-                r5,
-            ],
+            predictions: predict_with(&self.rs, &taylor_factors(dt)),
         }
     }
 }
 
-impl<T: Copy + Sub<T, Output = T> + Add<T, Output = T> + Debug> GearCorrector<T> {
-    pub fn correct<F: Float + Debug>(&self, r2: T, dt: F) -> [T; 6]
+impl<T, const N: usize> GearCorrector<T, N> {
+    /// Corrects the predictions with the acceleration `r2` evaluated at them.
+    pub fn correct<S: RealField + Copy>(&self, r2: T, dt: S, coefficients: Coefficients) -> [T; N]
     where
-        T: Div<F, Output = T> + Mul<F, Output = T>,
-        F: Mul<T, Output = T>,
+        T: Derivative<S>,
     {
-        let [rp, r1p, r2p, r3p, r4p, r5p] = self.predictions;
-
-        // Calculate the deltas
-        let da = r2 - r2p;
-        let dr2 = da * dt.powi(2) / fac_f(2); // delta r2
-
-        // Calculate the corrections
-        [
-            rp + F::from(3.0 / 16.0).unwrap() * dr2,
-            r1p + F::from(251.0 / 360.0).unwrap() * dr2 / dt,
-            r2p + F::from(1.0).unwrap() * dr2 * fac_f(2) / dt.powi(2),
-            r3p + F::from(11.0 / 18.0).unwrap() * dr2 * fac_f(3) / dt.powi(3),
-            r4p + F::from(1.0 / 6.0).unwrap() * dr2 * fac_f(4) / dt.powi(4),
-            r5p + F::from(1.0 / 60.0).unwrap() * dr2 * fac_f(5) / dt.powi(5),
-        ]
+        correct_with(
+            &self.predictions,
+            r2,
+            dt.powi(2) / fac_f(2),
+            &correction_factors(dt, coefficients),
+        )
+    }
+}
+
+/// Predicts every particle `dt` ahead, replacing its position and derivatives with the
+/// predictions.
+pub fn predict_all<S: RealField + Copy, T: Derivative<S>, const N: usize>(
+    particles: &mut [[T; N]],
+    dt: S,
+) {
+    let taylor = taylor_factors(dt);
+    for rs in particles {
+        *rs = predict_with(rs, &taylor);
+    }
+}
+
+/// Corrects the predictions of every particle with the accelerations evaluated at them.
+pub fn correct_all<S: RealField + Copy, T: Derivative<S>, const N: usize>(
+    predictions: &mut [[T; N]],
+    accelerations: &[T],
+    dt: S,
+    coefficients: Coefficients,
+) {
+    let half_dt_squared = dt.powi(2) / fac_f(2);
+    let factors = correction_factors(dt, coefficients);
+    for (predictions, &r2) in predictions.iter_mut().zip(accelerations) {
+        *predictions = correct_with(predictions, r2, half_dt_squared, &factors);
+    }
+}
+
+/// How the derivatives above the acceleration are estimated at the start of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Initialization {
+    /// They start at zero, which is only right when the acceleration is constant.
+    Zero,
+    /// Central differences of the acceleration at states extrapolated with the Taylor series of
+    /// the derivatives, which are refined a few times.
+    FiniteDifferences,
+    /// Central differences of the acceleration at states integrated with Runge-Kutta 4.
+    Bootstrap,
+}
+
+impl FromStr for Initialization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zero" => Ok(Initialization::Zero),
+            "finite-differences" => Ok(Initialization::FiniteDifferences),
+            "bootstrap" => Ok(Initialization::Bootstrap),
+            _ => Err(format!(
+                "unknown initialization `{s}`, expected `zero`, `finite-differences` or `bootstrap`"
+            )),
+        }
+    }
+}
+
+impl Display for Initialization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Initialization::Zero => "zero",
+            Initialization::FiniteDifferences => "finite-differences",
+            Initialization::Bootstrap => "bootstrap",
+        })
+    }
+}
+
+/// Refinements of the finite differences, each one gains an order of accuracy in the
+/// extrapolated states.
+const REFINEMENTS: usize = 4;
+/// Runge-Kutta steps between the points of the bootstrap.
+const BOOTSTRAP_SUBSTEPS: usize = 10;
+/// Offsets of the points of the stencil in steps, without the center.
+const OFFSETS: [i32; 4] = [-2, -1, 1, 2];
+
+/// Position, velocity and derivatives up to the order `N - 1` of every particle at `time`. The
+/// ones above the acceleration are estimated with `initialization` from points `h` apart.
+pub fn initial_derivatives<S, T, A, const N: usize>(
+    initialization: Initialization,
+    time: S,
+    positions: &[T],
+    velocities: &[T],
+    h: S,
+    mut acceleration: A,
+) -> Vec<[T; N]>
+where
+    S: RealField + Copy,
+    T: Derivative<S>,
+    A: FnMut(S, &[T], &[T], &mut [T]),
+{
+    #[allow(clippy::let_unit_value)]
+    let () = Terms::<N>::SUPPORTED;
+
+    let mut accelerations = vec![T::zero(); positions.len()];
+    acceleration(time, positions, velocities, &mut accelerations);
+    let mut rs: Vec<[T; N]> = positions
+        .iter()
+        .zip(velocities)
+        .zip(&accelerations)
+        .map(|((&r, &v), &a)| {
+            let mut rs = [T::zero(); N];
+            rs[0] = r;
+            rs[1] = v;
+            rs[2] = a;
+            rs
+        })
+        .collect();
+
+    if N == 3 {
+        return rs;
+    }
+
+    match initialization {
+        Initialization::Zero => {}
+        Initialization::FiniteDifferences => {
+            for _ in 0..REFINEMENTS {
+                let stencil = OFFSETS.map(|offset| {
+                    let dt = h * convert(offset as f64);
+                    let mut predictions = rs.clone();
+                    predict_all(&mut predictions, dt);
+                    let (positions, velocities): (Vec<_>, Vec<_>) =
+                        predictions.iter().map(|rs| (rs[0], rs[1])).unzip();
+                    let mut accelerations = vec![T::zero(); positions.len()];
+                    acceleration(time + dt, &positions, &velocities, &mut accelerations);
+                    accelerations
+                });
+                apply_stencil(&mut rs, &stencil, h);
+            }
+        }
+        Initialization::Bootstrap => {
+            let mut stencil = OFFSETS.map(|_| vec![]);
+            for direction in [-1, 1] {
+                let dt = h * convert(direction as f64 / BOOTSTRAP_SUBSTEPS as f64);
+                let mut positions = positions.to_vec();
+                let mut velocities = velocities.to_vec();
+                let mut t = time;
+
+                for steps in 1..=2 {
+                    for _ in 0..BOOTSTRAP_SUBSTEPS {
+                        runge_kutta_step(t, dt, &mut positions, &mut velocities, &mut acceleration);
+                        t += dt;
+                    }
+
+                    let offset = OFFSETS
+                        .iter()
+                        .position(|&offset| offset == direction * steps)
+                        .unwrap();
+                    stencil[offset] = vec![T::zero(); positions.len()];
+                    acceleration(t, &positions, &velocities, &mut stencil[offset]);
+                }
+            }
+            apply_stencil(&mut rs, &stencil, h);
+        }
+    }
+
+    rs
+}
+
+/// Sets the derivatives above the acceleration from the fourth order central differences of
+/// the accelerations at the `OFFSETS`.
+fn apply_stencil<S, T, const N: usize>(rs: &mut [[T; N]], stencil: &[Vec<T>; 4], h: S)
+where
+    S: RealField + Copy,
+    T: Derivative<S>,
+{
+    let constant = |value: f64| convert::<_, S>(value);
+    let [a_2, a_1, a1, a2] = stencil;
+
+    for (i, rs) in rs.iter_mut().enumerate() {
+        let a0 = rs[2];
+        let derivatives = [
+            (a_2[i] - a2[i] + (a1[i] - a_1[i]) * constant(8.0)) * (constant(1.0 / 12.0) / h),
+            ((a1[i] + a_1[i]) * constant(16.0) - a2[i] - a_2[i] - a0 * constant(30.0))
+                * (constant(1.0 / 12.0) / h.powi(2)),
+            (a2[i] - a_2[i] + (a_1[i] - a1[i]) * constant(2.0)) * (constant(0.5) / h.powi(3)),
+        ];
+
+        for (r, derivative) in rs[3..].iter_mut().zip(derivatives) {
+            *r = derivative;
+        }
+    }
+}
+
+/// Classic Runge-Kutta 4 step of the second order equation of every particle.
+fn runge_kutta_step<S, T, A>(
+    t: S,
+    dt: S,
+    positions: &mut [T],
+    velocities: &mut [T],
+    acceleration: &mut A,
+) where
+    S: RealField + Copy,
+    T: Derivative<S>,
+    A: FnMut(S, &[T], &[T], &mut [T]),
+{
+    let half = dt / convert(2.0);
+    let mut velocity_slopes: Vec<Vec<T>> = vec![];
+    let mut acceleration_slopes: Vec<Vec<T>> = vec![];
+
+    for (fraction, step) in [(S::zero(), S::zero()), (half, half), (half, half), (dt, dt)] {
+        let (stage_positions, stage_velocities): (Vec<_>, Vec<_>) =
+            match (velocity_slopes.last(), acceleration_slopes.last()) {
+                (Some(v_slope), Some(a_slope)) => positions
+                    .iter()
+                    .zip(velocities.iter())
+                    .zip(v_slope.iter().zip(a_slope))
+                    .map(|((&r, &v), (&k_r, &k_v))| (r + k_r * step, v + k_v * step))
+                    .unzip(),
+                _ => (positions.to_vec(), velocities.to_vec()),
+            };
+
+        let mut slopes = vec![T::zero(); positions.len()];
+        acceleration(
+            t + fraction,
+            &stage_positions,
+            &stage_velocities,
+            &mut slopes,
+        );
+        velocity_slopes.push(stage_velocities);
+        acceleration_slopes.push(slopes);
+    }
+
+    let weights = [1.0, 2.0, 2.0, 1.0].map(|w| dt * convert(w / 6.0));
+    for (i, (r, v)) in positions.iter_mut().zip(velocities.iter_mut()).enumerate() {
+        for ((v_slope, a_slope), &weight) in velocity_slopes
+            .iter()
+            .zip(&acceleration_slopes)
+            .zip(&weights)
+        {
+            *r = *r + v_slope[i] * weight;
+            *v = *v + a_slope[i] * weight;
+        }
     }
 }
@@ -0,0 +1,195 @@
+use num_traits::Float;
+
+use crate::{
+    constant, euler_step, remove_initialized, Acceleration, Integrator, Magnitude, Vector,
+};
+
+/// Evaluates the accelerations at the start of the step, and the previous ones with an Euler
+/// step back on the first step.
+fn start_step<S: Float, T: Vector<S>>(
+    time: S,
+    dt: S,
+    positions: &[T],
+    velocities: &[T],
+    accelerations: &mut Vec<T>,
+    previous_accelerations: &mut Vec<T>,
+    acceleration: &mut Acceleration<'_, S, T>,
+) {
+    accelerations.resize(positions.len(), T::zero());
+    acceleration(time, positions, velocities, accelerations);
+
+    if previous_accelerations.is_empty() {
+        let (previous_positions, previous_velocities): (Vec<_>, Vec<_>) = positions
+            .iter()
+            .zip(velocities)
+            .zip(accelerations.iter())
+            .map(|((&r, &v), &a)| euler_step(r, v, a, -dt))
+            .unzip();
+        previous_accelerations.resize(positions.len(), T::zero());
+        acceleration(
+            time - dt,
+            &previous_positions,
+            &previous_velocities,
+            previous_accelerations,
+        );
+    }
+}
+
+/// Beeman predictor-corrector for velocity dependent forces. The acceleration at the end of the
+/// step is evaluated at the new position and the predicted velocity, and the velocity is
+/// corrected with it up to `corrector_iterations` times, until it changes less than the
+/// tolerance.
+#[derive(Debug)]
+pub struct Beeman<S, T> {
+    corrector_iterations: usize,
+    tolerance: S,
+    previous_accelerations: Vec<T>,
+    accelerations: Vec<T>,
+    next_accelerations: Vec<T>,
+    start_velocities: Vec<T>,
+}
+
+impl<S, T> Beeman<S, T> {
+    pub fn new(corrector_iterations: usize, tolerance: S) -> Self {
+        assert!(
+            corrector_iterations >= 1,
+            "Beeman needs at least one correction"
+        );
+        Self {
+            corrector_iterations,
+            tolerance,
+            previous_accelerations: vec![],
+            accelerations: vec![],
+            next_accelerations: vec![],
+            start_velocities: vec![],
+        }
+    }
+}
+
+/// A single correction, the usual Beeman predictor-corrector.
+impl<S: Float, T> Default for Beeman<S, T> {
+    fn default() -> Self {
+        Self::new(1, S::zero())
+    }
+}
+
+impl<S: Float, T: Vector<S> + Magnitude<S>> Integrator<S, T> for Beeman<S, T> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        start_step(
+            time,
+            dt,
+            positions,
+            velocities,
+            &mut self.accelerations,
+            &mut self.previous_accelerations,
+            acceleration,
+        );
+
+        self.start_velocities.clear();
+        self.start_velocities.extend_from_slice(velocities);
+        for (((r, v), &prev_a), &a) in positions
+            .iter_mut()
+            .zip(velocities.iter_mut())
+            .zip(&self.previous_accelerations)
+            .zip(&self.accelerations)
+        {
+            *r = *r + *v * dt + a * (constant::<S>(2.0 / 3.0) * dt.powi(2))
+                - prev_a * (constant::<S>(1.0 / 6.0) * dt.powi(2));
+            *v = *v + a * (constant::<S>(1.5) * dt) - prev_a * (constant::<S>(0.5) * dt);
+        }
+
+        self.next_accelerations.resize(positions.len(), T::zero());
+        for _ in 0..self.corrector_iterations {
+            acceleration(
+                time + dt,
+                positions,
+                velocities,
+                &mut self.next_accelerations,
+            );
+
+            let mut change = S::zero();
+            for ((((v, &start_v), &next_a), &a), &prev_a) in velocities
+                .iter_mut()
+                .zip(&self.start_velocities)
+                .zip(&self.next_accelerations)
+                .zip(&self.accelerations)
+                .zip(&self.previous_accelerations)
+            {
+                let corrected_v =
+                    start_v + next_a * (dt / constant(3.0)) + a * (constant::<S>(5.0 / 6.0) * dt)
+                        - prev_a * (dt / constant(6.0));
+                change = change.max((corrected_v - *v).magnitude());
+                *v = corrected_v;
+            }
+
+            if change <= self.tolerance {
+                break;
+            }
+        }
+
+        std::mem::swap(&mut self.previous_accelerations, &mut self.accelerations);
+    }
+
+    fn remove(&mut self, index: usize) {
+        remove_initialized(&mut self.previous_accelerations, index);
+    }
+}
+
+/// Beeman algorithm as TP4A first had it. The acceleration at the end of the step is
+/// extrapolated from the current and previous ones instead of evaluating the forces again, so
+/// velocity dependent forces are never corrected. Kept for comparison with `Beeman`.
+#[derive(Debug, Default)]
+pub struct ExtrapolatedBeeman<T> {
+    previous_accelerations: Vec<T>,
+    accelerations: Vec<T>,
+}
+
+impl<S: Float, T: Vector<S>> Integrator<S, T> for ExtrapolatedBeeman<T> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        start_step(
+            time,
+            dt,
+            positions,
+            velocities,
+            &mut self.accelerations,
+            &mut self.previous_accelerations,
+            acceleration,
+        );
+
+        for (((r, v), prev_a), &a) in positions
+            .iter_mut()
+            .zip(velocities.iter_mut())
+            .zip(self.previous_accelerations.iter_mut())
+            .zip(&self.accelerations)
+        {
+            *r = *r + *v * dt + a * (constant::<S>(2.0 / 3.0) * dt.powi(2))
+                - *prev_a * (constant::<S>(1.0 / 6.0) * dt.powi(2));
+
+            let predicted_v =
+                *v + a * (constant::<S>(1.5) * dt) - *prev_a * (constant::<S>(0.5) * dt);
+            let next_a = (predicted_v - *v) * (S::one() / dt);
+
+            *v = *v + next_a * (dt / constant(3.0)) + a * (constant::<S>(5.0 / 6.0) * dt)
+                - *prev_a * (dt / constant(6.0));
+            *prev_a = a;
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        remove_initialized(&mut self.previous_accelerations, index);
+    }
+}
@@ -0,0 +1,365 @@
+use num_traits::Float;
+
+use crate::{constant, Acceleration, Integrator, Magnitude, Vector};
+
+const STAGES: usize = 7;
+
+/// Fractions of the step where every stage is evaluated.
+const C: [f64; STAGES] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+
+/// Weights of the previous stages in every stage. The last stage is the fifth order solution, so
+/// its slope is the first one of the next step.
+const A: [&[f64]; STAGES] = [
+    &[],
+    &[1.0 / 5.0],
+    &[3.0 / 40.0, 9.0 / 40.0],
+    &[44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0],
+    &[
+        19372.0 / 6561.0,
+        -25360.0 / 2187.0,
+        64448.0 / 6561.0,
+        -212.0 / 729.0,
+    ],
+    &[
+        9017.0 / 3168.0,
+        -355.0 / 33.0,
+        46732.0 / 5247.0,
+        49.0 / 176.0,
+        -5103.0 / 18656.0,
+    ],
+    &[
+        35.0 / 384.0,
+        0.0,
+        500.0 / 1113.0,
+        125.0 / 192.0,
+        -2187.0 / 6784.0,
+        11.0 / 84.0,
+    ],
+];
+
+/// Difference between the weights of the fifth and the fourth order solutions.
+const E: [f64; STAGES] = [
+    71.0 / 57600.0,
+    0.0,
+    -71.0 / 16695.0,
+    71.0 / 1920.0,
+    -17253.0 / 339200.0,
+    22.0 / 525.0,
+    -1.0 / 40.0,
+];
+
+/// Weights of the fourth order dense output.
+const D: [f64; STAGES] = [
+    -12715105075.0 / 11282082432.0,
+    0.0,
+    87487479700.0 / 32700410799.0,
+    -10690763975.0 / 1880347072.0,
+    701980252875.0 / 199316789632.0,
+    -1453857185.0 / 822651844.0,
+    69997945.0 / 29380423.0,
+];
+
+#[derive(Debug, Copy, Clone)]
+pub struct Tolerances<S> {
+    pub absolute: S,
+    pub relative: S,
+}
+
+/// Coefficients of the dense output over the last step of either the positions or the
+/// velocities.
+#[derive(Debug)]
+struct DenseOutput<T> {
+    coefficients: [Vec<T>; 5],
+}
+
+impl<T> Default for DenseOutput<T> {
+    fn default() -> Self {
+        Self {
+            coefficients: Default::default(),
+        }
+    }
+}
+
+impl<T> DenseOutput<T> {
+    fn remove(&mut self, index: usize) {
+        for coefficients in &mut self.coefficients {
+            coefficients.remove(index);
+        }
+    }
+}
+
+/// State of every particle and the slopes of the stages, which are the velocities for the
+/// positions and the accelerations for the velocities.
+#[derive(Debug)]
+struct Stages<T> {
+    positions: Vec<T>,
+    velocities: Vec<T>,
+    velocity_slopes: [Vec<T>; STAGES],
+    acceleration_slopes: [Vec<T>; STAGES],
+}
+
+impl<T> Default for Stages<T> {
+    fn default() -> Self {
+        Self {
+            positions: vec![],
+            velocities: vec![],
+            velocity_slopes: Default::default(),
+            acceleration_slopes: Default::default(),
+        }
+    }
+}
+
+/// Dormand-Prince 5(4) embedded Runge-Kutta method. It takes steps as long as the tolerances
+/// allow, and `step` interpolates the state at the requested time with the dense output, so the
+/// requested `dt` does not limit the length of the steps.
+///
+/// After the first step the positions and velocities are read from the internal state, so they
+/// must not be changed between steps, except to remove particles.
+#[derive(Debug)]
+pub struct DormandPrince<S, T> {
+    tolerances: Tolerances<S>,
+    /// Time of the internal state and length of the next step, once initialized.
+    time: S,
+    step_size: Option<S>,
+    /// Time and length of the last accepted step.
+    step_start: S,
+    last_step_size: S,
+    positions: Vec<T>,
+    velocities: Vec<T>,
+    stages: Stages<T>,
+    position_output: DenseOutput<T>,
+    velocity_output: DenseOutput<T>,
+}
+
+impl<S: Float, T> DormandPrince<S, T> {
+    pub fn new(tolerances: Tolerances<S>) -> Self {
+        assert!(
+            tolerances.absolute > S::zero() && tolerances.relative > S::zero(),
+            "The Dormand-Prince tolerances must be positive."
+        );
+        Self {
+            tolerances,
+            time: S::zero(),
+            step_size: None,
+            step_start: S::zero(),
+            last_step_size: S::zero(),
+            positions: vec![],
+            velocities: vec![],
+            stages: Default::default(),
+            position_output: Default::default(),
+            velocity_output: Default::default(),
+        }
+    }
+}
+
+/// `base + h * sum(weights * slopes)` for the particle `i`.
+fn weighted_sum<S: Float, T: Vector<S>>(
+    base: T,
+    slopes: &[Vec<T>],
+    weights: &[f64],
+    h: S,
+    i: usize,
+) -> T {
+    slopes
+        .iter()
+        .zip(weights)
+        .filter(|(_, &weight)| weight != 0.0)
+        .fold(base, |sum, (slope, &weight)| {
+            sum + slope[i] * (constant::<S>(weight) * h)
+        })
+}
+
+/// Dense output coefficients of either the positions or the velocities over a step of length
+/// `h` from `start` to `end`.
+fn dense_output<S: Float, T: Vector<S>>(
+    output: &mut DenseOutput<T>,
+    start: &[T],
+    end: &[T],
+    slopes: &[Vec<T>; STAGES],
+    h: S,
+) {
+    for coefficients in &mut output.coefficients {
+        coefficients.clear();
+    }
+
+    for (i, (&y0, &y1)) in start.iter().zip(end).enumerate() {
+        let difference = y1 - y0;
+        let first_slope = slopes[0][i] * h - difference;
+        let [c0, c1, c2, c3, c4] = &mut output.coefficients;
+        c0.push(y0);
+        c1.push(difference);
+        c2.push(first_slope);
+        c3.push(difference - slopes[STAGES - 1][i] * h - first_slope);
+        c4.push(weighted_sum(T::zero(), slopes, &D, h, i));
+    }
+}
+
+/// Value at the fraction `theta` of the last step.
+fn interpolate<S: Float, T: Vector<S>>(output: &DenseOutput<T>, theta: S, values: &mut [T]) {
+    let [c0, c1, c2, c3, c4] = &output.coefficients;
+    let rest = S::one() - theta;
+
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = c0[i] + (c1[i] + (c2[i] + (c3[i] + c4[i] * rest) * theta) * rest) * theta;
+    }
+}
+
+impl<S: Float, T: Vector<S> + Magnitude<S>> DormandPrince<S, T> {
+    /// Computes every stage after the first one with a step of length `h`, leaving the fifth
+    /// order solution in the stage positions and velocities, and returns the error relative to
+    /// the tolerances.
+    fn attempt(&mut self, h: S, acceleration: &mut Acceleration<'_, S, T>) -> S {
+        let stages = &mut self.stages;
+
+        for stage in 1..STAGES {
+            stages.positions.clear();
+            stages.velocities.clear();
+            for (i, (&r, &v)) in self.positions.iter().zip(&self.velocities).enumerate() {
+                stages.positions.push(weighted_sum(
+                    r,
+                    &stages.velocity_slopes[..stage],
+                    A[stage],
+                    h,
+                    i,
+                ));
+                stages.velocities.push(weighted_sum(
+                    v,
+                    &stages.acceleration_slopes[..stage],
+                    A[stage],
+                    h,
+                    i,
+                ));
+            }
+
+            stages.velocity_slopes[stage].clone_from(&stages.velocities);
+            let slopes = &mut stages.acceleration_slopes[stage];
+            slopes.resize(self.positions.len(), T::zero());
+            acceleration(
+                self.time + constant::<S>(C[stage]) * h,
+                &stages.positions,
+                &stages.velocities,
+                slopes,
+            );
+        }
+
+        let Tolerances { absolute, relative } = self.tolerances;
+        let mut sum = S::zero();
+        for i in 0..self.positions.len() {
+            for (start, end, slopes) in [
+                (&self.positions, &stages.positions, &stages.velocity_slopes),
+                (
+                    &self.velocities,
+                    &stages.velocities,
+                    &stages.acceleration_slopes,
+                ),
+            ] {
+                let error = weighted_sum(T::zero(), slopes, &E, h, i);
+                let scale = absolute + relative * start[i].magnitude().max(end[i].magnitude());
+                sum = sum + (error.magnitude() / scale).powi(2);
+            }
+        }
+
+        let count = S::from(2 * self.positions.len()).unwrap().max(S::one());
+        (sum / count).sqrt()
+    }
+}
+
+impl<S: Float, T: Vector<S> + Magnitude<S>> Integrator<S, T> for DormandPrince<S, T> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        if self.step_size.is_none() {
+            self.time = time;
+            self.step_size = Some(dt);
+            self.positions = positions.to_vec();
+            self.velocities = velocities.to_vec();
+
+            let stages = &mut self.stages;
+            stages.velocity_slopes[0].clone_from(&self.velocities);
+            stages.acceleration_slopes[0].resize(positions.len(), T::zero());
+            acceleration(
+                time,
+                &self.positions,
+                &self.velocities,
+                &mut stages.acceleration_slopes[0],
+            );
+        }
+
+        let target = time + dt;
+        while self.time < target {
+            let h = self.step_size.unwrap();
+            assert!(
+                self.time + h > self.time,
+                "The Dormand-Prince step size is too small to advance the time."
+            );
+            let error = self.attempt(h, acceleration);
+
+            // A NaN error, from a state that blew up, rejects the step and shrinks it the most.
+            let factor = if error.is_nan() {
+                constant(0.2)
+            } else if error > S::zero() {
+                (constant::<S>(0.9) * error.powf(constant(-0.2)))
+                    .max(constant(0.2))
+                    .min(constant(5.0))
+            } else {
+                constant(5.0)
+            };
+
+            if error <= S::one() {
+                let stages = &mut self.stages;
+                dense_output(
+                    &mut self.position_output,
+                    &self.positions,
+                    &stages.positions,
+                    &stages.velocity_slopes,
+                    h,
+                );
+                dense_output(
+                    &mut self.velocity_output,
+                    &self.velocities,
+                    &stages.velocities,
+                    &stages.acceleration_slopes,
+                    h,
+                );
+
+                std::mem::swap(&mut self.positions, &mut stages.positions);
+                std::mem::swap(&mut self.velocities, &mut stages.velocities);
+                // The slope at the end of the step is the first one of the next step.
+                stages.velocity_slopes.swap(0, STAGES - 1);
+                stages.acceleration_slopes.swap(0, STAGES - 1);
+
+                self.step_start = self.time;
+                self.last_step_size = h;
+                self.time = self.time + h;
+            }
+
+            self.step_size = Some(h * factor);
+        }
+
+        let theta = (target - self.step_start) / self.last_step_size;
+        interpolate(&self.position_output, theta, positions);
+        interpolate(&self.velocity_output, theta, velocities);
+    }
+
+    fn remove(&mut self, index: usize) {
+        if self.step_size.is_none() {
+            return;
+        }
+
+        self.positions.remove(index);
+        self.velocities.remove(index);
+        for slopes in [
+            &mut self.stages.velocity_slopes[0],
+            &mut self.stages.acceleration_slopes[0],
+        ] {
+            slopes.remove(index);
+        }
+        self.position_output.remove(index);
+        self.velocity_output.remove(index);
+    }
+}
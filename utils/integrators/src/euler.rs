@@ -0,0 +1,30 @@
+use num_traits::Float;
+
+use crate::{euler_step, Acceleration, Integrator, Vector};
+
+#[derive(Debug, Default)]
+pub struct Euler<T> {
+    accelerations: Vec<T>,
+}
+
+impl<S: Float, T: Vector<S>> Integrator<S, T> for Euler<T> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        self.accelerations.resize(positions.len(), T::zero());
+        acceleration(time, positions, velocities, &mut self.accelerations);
+
+        for ((r, v), &a) in positions
+            .iter_mut()
+            .zip(velocities.iter_mut())
+            .zip(&self.accelerations)
+        {
+            (*r, *v) = euler_step(*r, *v, a, dt);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use gear_predictor_corrector::{
+    correct_all, initial_derivatives, predict_all, Coefficients, Initialization,
+};
+use nalgebra::RealField;
+use num_traits::{Float, Zero};
+
+use crate::{remove_initialized, Acceleration, Integrator, Vector};
+
+/// Gear predictor-corrector with `N` terms, of order `N - 1` from 2 to 5.
+#[derive(Debug)]
+pub struct Gear<T, const N: usize> {
+    coefficients: Coefficients,
+    initialization: Initialization,
+    /// Position of every particle and its derivatives up to the order.
+    derivatives: Vec<[T; N]>,
+    accelerations: Vec<T>,
+}
+
+impl<T, const N: usize> Gear<T, N> {
+    /// The derivatives are evaluated on the first step, estimating the ones above the
+    /// acceleration with `initialization` from points a step apart.
+    pub fn new(coefficients: Coefficients, initialization: Initialization) -> Self {
+        Self {
+            coefficients,
+            initialization,
+            derivatives: vec![],
+            accelerations: vec![],
+        }
+    }
+}
+
+impl<T: Copy + Zero, const N: usize> Gear<T, N> {
+    /// Starts from the derivatives of the position of every particle, from the acceleration up to
+    /// the order. The position and velocity are taken from the first step.
+    pub fn with_derivatives(coefficients: Coefficients, derivatives: Vec<Vec<T>>) -> Self {
+        assert!(derivatives.iter().all(|d| d.len() == N - 2));
+        Self {
+            derivatives: derivatives
+                .into_iter()
+                .map(|d| {
+                    let mut rs = [T::zero(); N];
+                    rs[2..].copy_from_slice(&d);
+                    rs
+                })
+                .collect(),
+            ..Self::new(coefficients, Initialization::Zero)
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> Gear<T, N> {
+    /// Copies the position and velocity of every particle out of its derivatives.
+    fn write_state(&self, positions: &mut [T], velocities: &mut [T]) {
+        for ((r, v), rs) in positions
+            .iter_mut()
+            .zip(velocities.iter_mut())
+            .zip(&self.derivatives)
+        {
+            *r = rs[0];
+            *v = rs[1];
+        }
+    }
+}
+
+impl<S: Float + RealField, T: Vector<S>, const N: usize> Integrator<S, T> for Gear<T, N> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        self.accelerations.resize(positions.len(), T::zero());
+
+        if self.derivatives.is_empty() {
+            self.derivatives = initial_derivatives(
+                self.initialization,
+                time,
+                positions,
+                velocities,
+                dt,
+                &mut *acceleration,
+            );
+        }
+
+        for ((&r, &v), rs) in positions
+            .iter()
+            .zip(velocities.iter())
+            .zip(&mut self.derivatives)
+        {
+            rs[0] = r;
+            rs[1] = v;
+        }
+
+        predict_all(&mut self.derivatives, dt);
+        self.write_state(positions, velocities);
+
+        acceleration(time + dt, positions, velocities, &mut self.accelerations);
+
+        correct_all(
+            &mut self.derivatives,
+            &self.accelerations,
+            dt,
+            self.coefficients,
+        );
+        self.write_state(positions, velocities);
+    }
+
+    fn remove(&mut self, index: usize) {
+        remove_initialized(&mut self.derivatives, index);
+    }
+}
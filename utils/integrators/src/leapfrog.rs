@@ -0,0 +1,51 @@
+use num_traits::Float;
+
+use crate::{constant, remove_initialized, Acceleration, Integrator, Vector};
+
+/// Leapfrog keeps the velocities at the half steps. The velocities at the full steps are the half
+/// step ones moved half a step with the last acceleration.
+#[derive(Debug, Default)]
+pub struct Leapfrog<T> {
+    half_step_velocities: Vec<T>,
+    accelerations: Vec<T>,
+}
+
+impl<S: Float, T: Vector<S>> Integrator<S, T> for Leapfrog<T> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        let half_dt = dt / constant(2.0);
+
+        self.accelerations.resize(positions.len(), T::zero());
+        acceleration(time, positions, velocities, &mut self.accelerations);
+
+        if self.half_step_velocities.is_empty() {
+            self.half_step_velocities.extend(
+                velocities
+                    .iter()
+                    .zip(&self.accelerations)
+                    .map(|(&v, &a)| v - a * half_dt),
+            );
+        }
+
+        for (((r, v), half_v), &a) in positions
+            .iter_mut()
+            .zip(velocities.iter_mut())
+            .zip(self.half_step_velocities.iter_mut())
+            .zip(&self.accelerations)
+        {
+            *half_v = *half_v + a * dt;
+            *r = *r + *half_v * dt;
+            *v = *half_v + a * half_dt;
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        remove_initialized(&mut self.half_step_velocities, index);
+    }
+}
@@ -0,0 +1,95 @@
+use nalgebra::{RealField, SVector};
+use num_traits::{Float, Zero};
+use std::ops::{Add, Mul, Sub};
+
+mod beeman;
+mod dormand_prince;
+mod euler;
+mod gear;
+mod leapfrog;
+mod runge_kutta;
+mod verlet;
+
+pub use beeman::{Beeman, ExtrapolatedBeeman};
+pub use dormand_prince::{DormandPrince, Tolerances};
+pub use euler::Euler;
+pub use gear::Gear;
+pub use gear_predictor_corrector::{
+    Coefficients as GearCoefficients, Initialization as GearInitialization,
+};
+pub use leapfrog::Leapfrog;
+pub use runge_kutta::RungeKutta4;
+pub use verlet::{PositionVerlet, VelocityVerlet};
+
+/// Position, velocity or acceleration of one particle, like a scalar or a nalgebra vector.
+pub trait Vector<S>:
+    Copy + Zero + Add<Self, Output = Self> + Sub<Self, Output = Self> + Mul<S, Output = Self>
+{
+}
+
+impl<S, T> Vector<S> for T where
+    T: Copy + Zero + Add<T, Output = T> + Sub<T, Output = T> + Mul<S, Output = T>
+{
+}
+
+/// Size of a position or velocity, to compare the errors of the adaptive integrators against the
+/// tolerances.
+pub trait Magnitude<S> {
+    fn magnitude(&self) -> S;
+}
+
+impl Magnitude<f32> for f32 {
+    fn magnitude(&self) -> f32 {
+        self.abs()
+    }
+}
+
+impl Magnitude<f64> for f64 {
+    fn magnitude(&self) -> f64 {
+        self.abs()
+    }
+}
+
+impl<S: RealField + Copy, const D: usize> Magnitude<S> for SVector<S, D> {
+    fn magnitude(&self) -> S {
+        self.norm()
+    }
+}
+
+/// Computes the acceleration of every particle from the time, the positions and the velocities.
+pub type Acceleration<'a, S, T> = dyn FnMut(S, &[T], &[T], &mut [T]) + 'a;
+
+/// Integrates the motion of a set of particles.
+///
+/// Integrators that need previous steps initialize them on the first call to `step`.
+pub trait Integrator<S: Float, T: Vector<S>> {
+    /// Moves the positions and velocities from `time` to `time + dt`.
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    );
+
+    /// Forgets the particle at `index`, which was removed from the positions and velocities.
+    fn remove(&mut self, _index: usize) {}
+}
+
+pub(crate) fn constant<S: Float>(value: f64) -> S {
+    S::from(value).unwrap()
+}
+
+/// Euler step with the second order term in the position, used to estimate the previous step
+/// with a negative `dt`.
+pub(crate) fn euler_step<S: Float, T: Vector<S>>(r: T, v: T, a: T, dt: S) -> (T, T) {
+    (r + v * dt + a * (dt.powi(2) / constant(2.0)), v + a * dt)
+}
+
+/// Removes the element at `index` if the buffer was already initialized.
+pub(crate) fn remove_initialized<T>(buffer: &mut Vec<T>, index: usize) {
+    if !buffer.is_empty() {
+        buffer.remove(index);
+    }
+}
@@ -0,0 +1,80 @@
+use num_traits::Float;
+
+use crate::{constant, Acceleration, Integrator, Vector};
+
+/// Classic fourth order Runge-Kutta over the positions and velocities.
+#[derive(Debug, Default)]
+pub struct RungeKutta4<T> {
+    stage_positions: Vec<T>,
+    /// Velocities and accelerations of every stage, which are the slopes of the positions and
+    /// the velocities.
+    stage_velocities: [Vec<T>; 4],
+    stage_accelerations: [Vec<T>; 4],
+}
+
+impl<S: Float, T: Vector<S>> Integrator<S, T> for RungeKutta4<T> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        let offsets: [S; 4] = [S::zero(), constant(0.5), constant(0.5), S::one()];
+        let weights: [S; 4] = [
+            constant(1.0 / 6.0),
+            constant(1.0 / 3.0),
+            constant(1.0 / 3.0),
+            constant(1.0 / 6.0),
+        ];
+
+        for (stage, &offset) in offsets.iter().enumerate() {
+            let (previous_velocities, stage_velocities) = self.stage_velocities.split_at_mut(stage);
+            let stage_velocities = &mut stage_velocities[0];
+            let h = offset * dt;
+
+            self.stage_positions.clear();
+            stage_velocities.clear();
+            if let Some(previous_velocities) = previous_velocities.last() {
+                let previous_accelerations = &self.stage_accelerations[stage - 1];
+                self.stage_positions.extend(
+                    positions
+                        .iter()
+                        .zip(previous_velocities)
+                        .map(|(&r, &k)| r + k * h),
+                );
+                stage_velocities.extend(
+                    velocities
+                        .iter()
+                        .zip(previous_accelerations)
+                        .map(|(&v, &k)| v + k * h),
+                );
+            } else {
+                self.stage_positions.extend_from_slice(positions);
+                stage_velocities.extend_from_slice(velocities);
+            }
+
+            let stage_accelerations = &mut self.stage_accelerations[stage];
+            stage_accelerations.resize(positions.len(), T::zero());
+            acceleration(
+                time + h,
+                &self.stage_positions,
+                stage_velocities,
+                stage_accelerations,
+            );
+        }
+
+        for (i, (r, v)) in positions.iter_mut().zip(velocities.iter_mut()).enumerate() {
+            for ((stage_velocities, stage_accelerations), &weight) in self
+                .stage_velocities
+                .iter()
+                .zip(&self.stage_accelerations)
+                .zip(&weights)
+            {
+                *r = *r + stage_velocities[i] * (weight * dt);
+                *v = *v + stage_accelerations[i] * (weight * dt);
+            }
+        }
+    }
+}
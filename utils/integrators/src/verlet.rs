@@ -0,0 +1,109 @@
+use num_traits::Float;
+
+use crate::{constant, euler_step, remove_initialized, Acceleration, Integrator, Vector};
+
+/// Original Verlet algorithm. The velocities are the centered difference of the positions, so
+/// they are estimated one step late.
+#[derive(Debug, Default)]
+pub struct PositionVerlet<T> {
+    previous_positions: Vec<T>,
+    accelerations: Vec<T>,
+}
+
+impl<S: Float, T: Vector<S>> Integrator<S, T> for PositionVerlet<T> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        self.accelerations.resize(positions.len(), T::zero());
+        acceleration(time, positions, velocities, &mut self.accelerations);
+
+        if self.previous_positions.is_empty() {
+            self.previous_positions.extend(
+                positions
+                    .iter()
+                    .zip(velocities.iter())
+                    .zip(&self.accelerations)
+                    .map(|((&r, &v), &a)| euler_step(r, v, a, -dt).0),
+            );
+        }
+
+        for (((r, v), prev_r), &a) in positions
+            .iter_mut()
+            .zip(velocities.iter_mut())
+            .zip(self.previous_positions.iter_mut())
+            .zip(&self.accelerations)
+        {
+            let next_r = *r * constant(2.0) - *prev_r + a * dt.powi(2);
+            *v = (next_r - *prev_r) * (constant::<S>(0.5) / dt);
+            *prev_r = *r;
+            *r = next_r;
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        remove_initialized(&mut self.previous_positions, index);
+    }
+}
+
+/// The acceleration at the end of the step is evaluated with the velocities predicted from the
+/// one at the start, so velocity dependent forces are only first order accurate.
+#[derive(Debug, Default)]
+pub struct VelocityVerlet<T> {
+    accelerations: Vec<T>,
+    next_accelerations: Vec<T>,
+    predicted_velocities: Vec<T>,
+}
+
+impl<S: Float, T: Vector<S>> Integrator<S, T> for VelocityVerlet<T> {
+    fn step(
+        &mut self,
+        time: S,
+        dt: S,
+        positions: &mut [T],
+        velocities: &mut [T],
+        acceleration: &mut Acceleration<'_, S, T>,
+    ) {
+        if self.accelerations.is_empty() {
+            self.accelerations.resize(positions.len(), T::zero());
+            acceleration(time, positions, velocities, &mut self.accelerations);
+        }
+
+        self.predicted_velocities.clear();
+        for ((r, &v), &a) in positions
+            .iter_mut()
+            .zip(velocities.iter())
+            .zip(&self.accelerations)
+        {
+            let (next_r, next_v) = euler_step(*r, v, a, dt);
+            *r = next_r;
+            self.predicted_velocities.push(next_v);
+        }
+
+        self.next_accelerations.resize(positions.len(), T::zero());
+        acceleration(
+            time + dt,
+            positions,
+            &self.predicted_velocities,
+            &mut self.next_accelerations,
+        );
+
+        for ((v, &a), &next_a) in velocities
+            .iter_mut()
+            .zip(&self.accelerations)
+            .zip(&self.next_accelerations)
+        {
+            *v = *v + (a + next_a) * (dt / constant(2.0));
+        }
+
+        std::mem::swap(&mut self.accelerations, &mut self.next_accelerations);
+    }
+
+    fn remove(&mut self, index: usize) {
+        remove_initialized(&mut self.accelerations, index);
+    }
+}
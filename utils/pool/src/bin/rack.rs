@@ -0,0 +1,122 @@
+use std::{
+    f64::consts::TAU,
+    fs::File,
+    io::{stdout, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use nalgebra::Vector2;
+use pool::{
+    models::{Ball, InputData, Pocket},
+    Float, WHITE_BALL_ID,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Generates an input with the white ball and the 15 balls racked in a triangle.
+#[derive(clap::Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long, default_value_t = 224.0)]
+    table_width: Float,
+
+    #[arg(long, default_value_t = 112.0)]
+    table_height: Float,
+
+    #[arg(long, default_value_t = 11.4)]
+    hole_diameter: Float,
+
+    #[arg(long, default_value_t = 5.7)]
+    ball_diameter: Float,
+
+    #[arg(long, default_value_t = 165.0)]
+    ball_mass: Float,
+
+    /// Height of the white ball, the center of the table by default
+    #[arg(long)]
+    white_y: Option<Float>,
+
+    /// Initial speed of the white ball, towards the rack
+    #[arg(long, default_value_t = 200.0)]
+    speed: Float,
+
+    /// Minimum gap between neighboring balls of the rack
+    #[arg(long, default_value_t = 0.02)]
+    min_gap: Float,
+
+    /// Maximum gap between neighboring balls of the rack
+    #[arg(long, default_value_t = 0.03)]
+    max_gap: Float,
+
+    #[arg(long)]
+    seed: Option<u64>,
+
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn random_inside_circle<R: Rng>(rng: &mut R, radius: Float) -> Vector2<Float> {
+    let r = radius * rng.gen::<Float>().sqrt();
+    let theta = rng.gen::<Float>() * TAU as Float;
+    Vector2::new(r * theta.cos(), r * theta.sin())
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut rng = if let Some(seed) = args.seed {
+        StdRng::seed_from_u64(seed)
+    } else {
+        StdRng::from_entropy()
+    };
+
+    let ball_radius = args.ball_diameter / 2.0;
+    let hole_radius = args.hole_diameter / 2.0;
+    let mut balls = vec![Ball {
+        id: WHITE_BALL_ID,
+        position: Vector2::new(
+            args.table_width / 4.0,
+            args.white_y.unwrap_or(args.table_height / 2.0),
+        ),
+        velocity: Vector2::new(args.speed, 0.0),
+        radius: ball_radius,
+        mass: args.ball_mass,
+    }];
+
+    // The balls are placed with the mean gap and then moved randomly, so any two neighbors end
+    // up between the minimum and the maximum gap.
+    let separation = args.ball_diameter + (args.min_gap + args.max_gap) / 2.0;
+    let max_displacement = (args.max_gap - args.min_gap) / 4.0;
+    let row_distance = separation * (3.0 as Float).sqrt() / 2.0;
+
+    for rank in 0..5 {
+        let x = args.table_width * 3.0 / 4.0 + rank as Float * row_distance;
+        for i in 0..=rank {
+            let y = args.table_height / 2.0 + (i as Float - rank as Float / 2.0) * separation;
+            balls.push(Ball {
+                id: balls.len(),
+                position: Vector2::new(x, y) + random_inside_circle(&mut rng, max_displacement),
+                velocity: Vector2::zeros(),
+                radius: ball_radius,
+                mass: args.ball_mass,
+            });
+        }
+    }
+
+    let input = InputData {
+        table_width: args.table_width,
+        table_height: args.table_height,
+        hole_radius,
+        ball_radius,
+        ball_mass: args.ball_mass,
+        balls,
+        pockets: Pocket::standard(args.table_width, args.table_height, hole_radius),
+        cushions: vec![],
+    };
+
+    let mut writer = if let Some(output) = args.output {
+        Box::new(File::create(output).unwrap()) as Box<dyn Write>
+    } else {
+        Box::new(stdout())
+    };
+    writer.write_fmt(format_args!("{input}")).unwrap();
+}
@@ -1,14 +1,13 @@
 use std::num::ParseIntError;
 
-use nalgebra::Vector2;
 use nannou::{
     color::{rgb_u32, Saturate, Shade},
     prelude::{Rgb, *},
 };
 
 use crate::{
-    models::{Ball, InputData},
-    Float,
+    models::{Ball, InputData, Pocket},
+    WHITE_BALL_ID,
 };
 
 fn parse_hex_color(s: &str) -> Result<Rgb<u8>, ParseIntError> {
@@ -18,25 +17,26 @@ fn parse_hex_color(s: &str) -> Result<Rgb<u8>, ParseIntError> {
 pub fn draw<BI: IntoIterator<Item = Ball>>(
     system_info: &InputData,
     balls: BI,
-    holes: &[Vector2<Float>],
+    holes: &[Pocket],
     draw: &Draw,
 ) {
     let draw = draw.scale(1.0 / system_info.table_height as f32);
     draw.background().color(parse_hex_color("305A4A").unwrap());
 
     for particle in balls {
+        let radius = particle.radius as f32;
         let circle_border = draw
             .ellipse()
-            .radius(system_info.ball_radius as f32)
+            .radius(radius)
             .x(particle.position.x as f32)
             .y(particle.position.y as f32);
         let circle = draw
             .ellipse()
-            .radius(system_info.ball_radius as f32 - 0.5)
+            .radius((radius - 0.5).max(radius * 0.8))
             .x(particle.position.x as f32)
             .y(particle.position.y as f32);
 
-        if particle.id == 0 {
+        if particle.id == WHITE_BALL_ID {
             circle_border.color(WHITE).finish();
             circle.color(WHITE).finish();
         } else {
@@ -54,9 +54,9 @@ pub fn draw<BI: IntoIterator<Item = Ball>>(
 
     for hole in holes {
         draw.ellipse()
-            .radius(system_info.hole_radius as f32)
-            .x(hole.x as f32)
-            .y(hole.y as f32)
+            .radius(hole.radius as f32)
+            .x(hole.position.x as f32)
+            .y(hole.position.y as f32)
             //.no_fill()
             //.stroke_weight(1.0)
             //.stroke(GRAY)
@@ -79,4 +79,13 @@ pub fn draw<BI: IntoIterator<Item = Ball>>(
         .no_fill()
         .stroke_weight(system_info.hole_radius as f32)
         .stroke(hole_color);
+
+    for cushion in &system_info.cushions {
+        draw.line()
+            .start(vec2(cushion.start.x as f32, cushion.start.y as f32))
+            .end(vec2(cushion.end.x as f32, cushion.end.y as f32))
+            .weight(1.0)
+            .caps_round()
+            .color(hole_color);
+    }
 }
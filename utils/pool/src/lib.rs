@@ -1,3 +1,4 @@
+use cim::particles::ID;
 use nalgebra::Vector2;
 
 pub mod draw;
@@ -17,3 +18,5 @@ pub const HOLE_POSITIONS: [Vector2<Float>; 6] = [
     Vector2::new(0.5, 0.0),
     Vector2::new(0.5, 1.0),
 ];
+
+pub const WHITE_BALL_ID: ID = 0;
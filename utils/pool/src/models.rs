@@ -1,9 +1,9 @@
-use std::{fmt::Display, io::Write};
+use std::{collections::BTreeMap, fmt::Display, io::Write};
 
 use cim::particles::{CircularParticle, ID};
 use nalgebra::Vector2;
 
-use crate::Float;
+use crate::{Float, HOLE_POSITIONS};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ball {
@@ -11,6 +11,7 @@ pub struct Ball {
     pub position: Vector2<Float>,
     pub velocity: Vector2<Float>,
     pub radius: Float,
+    pub mass: Float,
 }
 
 impl CircularParticle for Ball {
@@ -19,7 +20,7 @@ impl CircularParticle for Ball {
     }
 
     fn get_position(&self) -> Vector2<f64> {
-        self.position
+        self.position.cast()
     }
 
     fn get_radius(&self) -> f64 {
@@ -27,14 +28,127 @@ impl CircularParticle for Ball {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pocket {
+    pub position: Vector2<Float>,
+    pub radius: Float,
+}
+
+impl Pocket {
+    /// Pockets in the corners and the middle of the long sides of the table.
+    pub fn standard(table_width: Float, table_height: Float, radius: Float) -> Vec<Self> {
+        HOLE_POSITIONS
+            .map(|v| Self {
+                position: v.component_mul(&Vector2::new(table_width, table_height)),
+                radius,
+            })
+            .to_vec()
+    }
+}
+
+/// Straight cushion the balls bounce against, besides the borders of the table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cushion {
+    pub start: Vector2<Float>,
+    pub end: Vector2<Float>,
+}
+
+impl Cushion {
+    /// Point of the cushion closest to `point`.
+    pub fn closest_point(&self, point: &Vector2<Float>) -> Vector2<Float> {
+        let direction = self.end - self.start;
+        let t =
+            ((point - self.start).dot(&direction) / direction.magnitude_squared()).clamp(0.0, 1.0);
+        self.start + direction * t
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct InputData {
     pub table_width: Float,
     pub table_height: Float,
+    /// Radius of the standard pockets, used when the input does not list them.
     pub hole_radius: Float,
+    /// Radius of the balls without their own radius column.
     pub ball_radius: Float,
+    /// Mass of the balls without their own mass column.
     pub ball_mass: Float,
     pub balls: Vec<Ball>,
+    pub pockets: Vec<Pocket>,
+    pub cushions: Vec<Cushion>,
+}
+
+impl InputData {
+    pub fn max_ball_radius(&self) -> Float {
+        self.balls
+            .iter()
+            .map(|ball| ball.radius)
+            .fold(self.ball_radius, Float::max)
+    }
+
+    /// Output files only have the kinematics of the balls, so their radius and mass are taken
+    /// from the input.
+    pub fn restore_properties(&self, balls: &mut [Ball]) {
+        let properties: BTreeMap<_, _> = self
+            .balls
+            .iter()
+            .map(|ball| (ball.id, (ball.radius, ball.mass)))
+            .collect();
+        for ball in balls {
+            if let Some(&(radius, mass)) = properties.get(&ball.id) {
+                ball.radius = radius;
+                ball.mass = mass;
+            }
+        }
+    }
+}
+
+/// Writes the input format, with the mass and radius columns only for the balls that differ
+/// from the defaults.
+impl Display for InputData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n",
+            self.table_width,
+            self.table_height,
+            self.hole_radius * 2.0,
+            self.ball_radius * 2.0,
+            self.ball_mass,
+            self.balls.len(),
+        ))?;
+        for ball in &self.balls {
+            f.write_fmt(format_args!(
+                "{} {} {} {} {}",
+                ball.id, ball.position.x, ball.position.y, ball.velocity.x, ball.velocity.y,
+            ))?;
+            if ball.mass != self.ball_mass || ball.radius != self.ball_radius {
+                f.write_fmt(format_args!(" {} {}", ball.mass, ball.radius))?;
+            }
+            f.write_str("\n")?;
+        }
+
+        if self.pockets != Pocket::standard(self.table_width, self.table_height, self.hole_radius) {
+            f.write_str("pockets\n")?;
+            for pocket in &self.pockets {
+                f.write_fmt(format_args!(
+                    "{} {} {}\n",
+                    pocket.position.x, pocket.position.y, pocket.radius
+                ))?;
+            }
+        }
+
+        if !self.cushions.is_empty() {
+            f.write_str("cushions\n")?;
+            for cushion in &self.cushions {
+                f.write_fmt(format_args!(
+                    "{} {} {} {}\n",
+                    cushion.start.x, cushion.start.y, cushion.end.x, cushion.end.y
+                ))?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1,7 +1,7 @@
 use std::io::{BufRead, Lines};
 
 use crate::{
-    models::{Ball, Frame, InputData},
+    models::{Ball, Cushion, Frame, InputData, Pocket},
     Float,
 };
 use chumsky::{prelude::*, text::newline};
@@ -22,11 +22,22 @@ pub fn input_parser<'a>() -> impl Parser<'a, &'a str, InputData, extra::Err<Rich
     let ball_data = unsigned
         .then_ignore(just(' '))
         .then(num.separated_by_exactly::<_, _, 4>(just(' ')))
-        .map(|(id, [x, y, vx, vy])| Ball {
-            id,
-            position: Vector2::new(x, y),
-            velocity: Vector2::new(vx, vy),
-            radius: 0.0,
+        .then(
+            just(' ')
+                .ignore_then(num.separated_by_exactly::<_, _, 2>(just(' ')))
+                .or_not(),
+        )
+        .map(|((id, [x, y, vx, vy]), properties)| {
+            (
+                Ball {
+                    id,
+                    position: Vector2::new(x, y),
+                    velocity: Vector2::new(vx, vy),
+                    radius: 0.0,
+                    mass: 0.0,
+                },
+                properties,
+            )
         });
 
     let balls = ball_data
@@ -35,6 +46,31 @@ pub fn input_parser<'a>() -> impl Parser<'a, &'a str, InputData, extra::Err<Rich
         .allow_trailing()
         .collect();
 
+    let point = num
+        .then_ignore(just(' '))
+        .then(num)
+        .map(|(x, y)| Vector2::new(x, y));
+
+    let pockets = just("pockets").ignore_then(newline()).ignore_then(
+        point
+            .then_ignore(just(' '))
+            .then(num)
+            .map(|(position, radius)| Pocket { position, radius })
+            .separated_by(newline())
+            .allow_trailing()
+            .collect::<Vec<_>>(),
+    );
+
+    let cushions = just("cushions").ignore_then(newline()).ignore_then(
+        point
+            .then_ignore(just(' '))
+            .then(point)
+            .map(|(start, end)| Cushion { start, end })
+            .separated_by(newline())
+            .allow_trailing()
+            .collect::<Vec<_>>(),
+    );
+
     num.then_ignore(newline())
         .then(num)
         .then_ignore(newline())
@@ -48,12 +84,23 @@ pub fn input_parser<'a>() -> impl Parser<'a, &'a str, InputData, extra::Err<Rich
         .map(|(((((w, h), h_r), r), m), n)| (w, h, h_r, r, m, n))
         .then_ignore(newline())
         .then(balls)
+        .then(pockets.or_not().then(cushions.or_not()))
         .map(
-            |((table_width, table_height, hole_radius, ball_radius, ball_mass, _), mut balls): (
-                _,
-                Vec<Ball>,
-            )| {
-                balls.iter_mut().for_each(|b| b.radius = ball_radius);
+            |(
+                ((table_width, table_height, hole_radius, ball_radius, ball_mass, _), balls),
+                (pockets, cushions),
+            ): ((_, Vec<(Ball, Option<[Float; 2]>)>), _)| {
+                let balls = balls
+                    .into_iter()
+                    .map(|(ball, properties)| {
+                        let [mass, radius] = properties.unwrap_or([ball_mass, ball_radius]);
+                        Ball {
+                            mass,
+                            radius,
+                            ..ball
+                        }
+                    })
+                    .collect();
                 InputData {
                     table_width,
                     table_height,
@@ -61,6 +108,10 @@ pub fn input_parser<'a>() -> impl Parser<'a, &'a str, InputData, extra::Err<Rich
                     ball_radius,
                     ball_mass,
                     balls,
+                    pockets: pockets.unwrap_or_else(|| {
+                        Pocket::standard(table_width, table_height, hole_radius)
+                    }),
+                    cushions: cushions.unwrap_or_default(),
                 }
             },
         )
@@ -117,6 +168,7 @@ pub fn output_parser<B: BufRead>(file: Lines<B>) -> impl Iterator<Item = Frame>
                     position: Vector2::new(x, y),
                     velocity: Vector2::new(vx, vy),
                     radius: 0.0,
+                    mass: 0.0,
                 }
             })
             .collect_vec();